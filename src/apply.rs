@@ -1,6 +1,7 @@
 use crate::{
-    patch::{Hunk, Line, Patch},
-    utils::LineIter,
+    diff::create_patch,
+    patch::{Hunk, Line, OwnedPatch, Patch},
+    utils::{without_cr, LineEnding, LineIter, Text},
 };
 use std::{fmt, iter};
 
@@ -8,26 +9,83 @@ use std::{fmt, iter};
 ///
 /// [`apply`]: fn.apply.html
 #[derive(Debug)]
-pub struct ApplyError(usize);
+pub struct ApplyError(ApplyErrorKind);
+
+#[derive(Debug)]
+enum ApplyErrorKind {
+    // The 1-based index of the hunk which couldn't be applied
+    Hunk(usize),
+    // A patch creating a file was applied to a non-empty base image
+    NotEmpty,
+    // A patch deleting a file left some lines of the base image
+    NotConsumed,
+    // A single hunk didn't match the base image at the 1-based line it was applied at, which may
+    // be out of bounds
+    HunkAt(isize),
+}
 
 impl fmt::Display for ApplyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "error applying hunk #{}", self.0)
+        match self.0 {
+            ApplyErrorKind::Hunk(i) => write!(f, "error applying hunk #{}", i),
+            ApplyErrorKind::NotEmpty => write!(
+                f,
+                "error applying patch: it creates a file but the base image isn't empty"
+            ),
+            ApplyErrorKind::NotConsumed => write!(
+                f,
+                "error applying patch: it deletes a file but not every line of the base image"
+            ),
+            ApplyErrorKind::HunkAt(line) => write!(f, "error applying hunk at line {}", line),
+        }
     }
 }
 
 impl std::error::Error for ApplyError {}
 
+/// An error returned by [`verify_apply`] when a `Patch` doesn't produce the expected result
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The patch couldn't be applied at all
+    Apply(ApplyError),
+    /// The patch applied, but the result differs from the expected one. Holds the patch from
+    /// the actual result, named `actual`, to the expected one, named `expected`.
+    Mismatch(OwnedPatch),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Apply(e) => e.fmt(f),
+            VerifyError::Mismatch(_) => write!(f, "patch result differs from the expected one"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerifyError::Apply(e) => Some(e),
+            VerifyError::Mismatch(_) => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum ImageLine<'a, T: ?Sized> {
     Unpatched(&'a T),
+    // A line inserted by a hunk, which is given the line ending of the base image
     Patched(&'a T),
+    // A line of a hunk which is written as it is, like the lines of the base image
+    PatchedAsIs(&'a T),
 }
 
 impl<'a, T: ?Sized> ImageLine<'a, T> {
     fn inner(&self) -> &'a T {
         match self {
-            ImageLine::Unpatched(inner) | ImageLine::Patched(inner) => inner,
+            ImageLine::Unpatched(inner)
+            | ImageLine::Patched(inner)
+            | ImageLine::PatchedAsIs(inner) => inner,
         }
     }
 
@@ -38,7 +96,7 @@ impl<'a, T: ?Sized> ImageLine<'a, T> {
     fn is_patched(&self) -> bool {
         match self {
             ImageLine::Unpatched(_) => false,
-            ImageLine::Patched(_) => true,
+            ImageLine::Patched(_) | ImageLine::PatchedAsIs(_) => true,
         }
     }
 }
@@ -51,8 +109,196 @@ impl<T: ?Sized> Clone for ImageLine<'_, T> {
     }
 }
 
+/// A collection of options for modifying the way a patch is applied
+#[derive(Debug)]
+pub struct ApplyOptions {
+    line_ending: LineEnding,
+    ignore_trailing_whitespace: bool,
+}
+
+impl ApplyOptions {
+    /// Constructs a new `ApplyOptions` with default settings
+    ///
+    /// ## Defaults
+    /// * line_ending = LineEnding::Auto
+    /// * ignore_trailing_whitespace = false
+    pub fn new() -> Self {
+        Self {
+            line_ending: LineEnding::Auto,
+            ignore_trailing_whitespace: false,
+        }
+    }
+
+    /// Set how line endings are treated, see [`LineEnding`]
+    ///
+    /// The base image decides the line ending when it is `LineEnding::Auto`.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) -> &mut Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Ignore trailing whitespace when matching the lines of a hunk against the base image, like
+    /// `patch --ignore-whitespace`
+    ///
+    /// This lets a patch apply after an editor or mail client stripped the trailing whitespace
+    /// off its lines. Context lines are kept as they are in the base image.
+    ///
+    /// ```
+    /// use diffy::{ApplyOptions, Patch};
+    ///
+    /// let patch = Patch::from_str("@@ -1,2 +1,2 @@\n a\n-b\n+B\n").unwrap();
+    /// let base_image = "a  \nb\t\n";
+    ///
+    /// assert!(ApplyOptions::new().apply(base_image, &patch).is_err());
+    /// let image = ApplyOptions::new()
+    ///     .set_ignore_trailing_whitespace(true)
+    ///     .apply(base_image, &patch)
+    ///     .unwrap();
+    /// assert_eq!(image, "a  \nB\n");
+    /// ```
+    pub fn set_ignore_trailing_whitespace(
+        &mut self,
+        ignore_trailing_whitespace: bool,
+    ) -> &mut Self {
+        self.ignore_trailing_whitespace = ignore_trailing_whitespace;
+        self
+    }
+
+    /// Apply a `Patch` to a base image based on the configured options
+    pub fn apply(&self, base_image: &str, patch: &Patch<'_, str>) -> Result<String, ApplyError> {
+        let (image, ending) = self.apply_impl(base_image, patch)?;
+        Ok(image_into_string(image, ending, self.base_ending(ending)))
+    }
+
+    /// Apply a non-utf8 `Patch` to a base image based on the configured options
+    pub fn apply_bytes(
+        &self,
+        base_image: &[u8],
+        patch: &Patch<'_, [u8]>,
+    ) -> Result<Vec<u8>, ApplyError> {
+        let (image, ending) = self.apply_impl(base_image, patch)?;
+        Ok(image_into_bytes(image, ending, self.base_ending(ending)))
+    }
+
+    /// Apply as many hunks of a `Patch` as possible to a base image based on the configured
+    /// options, see [`apply_partial`]
+    pub fn apply_partial<'a>(
+        &self,
+        base_image: &str,
+        patch: &Patch<'a, str>,
+    ) -> (String, Vec<Hunk<'a, str>>) {
+        let (image, ending, rejected) = self.apply_partial_impl(base_image, patch);
+        let image = image_into_string(image, ending, self.base_ending(ending));
+        (image, rejected)
+    }
+
+    /// Apply as many hunks of a non-utf8 `Patch` as possible to a base image based on the
+    /// configured options
+    pub fn apply_bytes_partial<'a>(
+        &self,
+        base_image: &[u8],
+        patch: &Patch<'a, [u8]>,
+    ) -> (Vec<u8>, Vec<Hunk<'a, [u8]>>) {
+        let (image, ending, rejected) = self.apply_partial_impl(base_image, patch);
+        let image = image_into_bytes(image, ending, self.base_ending(ending));
+        (image, rejected)
+    }
+
+    /// Apply a `Patch` to a base image based on the configured options and check the result
+    /// against `expected`, see [`verify_apply`]
+    pub fn verify_apply(
+        &self,
+        base_image: &str,
+        patch: &Patch<'_, str>,
+        expected: &str,
+    ) -> Result<(), VerifyError> {
+        let actual = self.apply(base_image, patch).map_err(VerifyError::Apply)?;
+        if actual == expected {
+            return Ok(());
+        }
+
+        let hunks = create_patch(&actual, expected).into_iter().collect();
+        let diff = Patch::new(Some("actual"), Some("expected"), hunks);
+        Err(VerifyError::Mismatch(diff.into_owned()))
+    }
+
+    fn matcher(&self, ending: Option<LineEnding>) -> LineMatcher {
+        LineMatcher {
+            ending,
+            ignore_trailing_whitespace: self.ignore_trailing_whitespace,
+        }
+    }
+
+    // Lines of the base image are only given the line ending if it was explicitly chosen
+    fn base_ending(&self, ending: Option<LineEnding>) -> Option<LineEnding> {
+        ending.filter(|_| self.line_ending != LineEnding::Auto)
+    }
+
+    fn apply_impl<'a, T: Text + ?Sized>(
+        &self,
+        base_image: &'a T,
+        patch: &Patch<'a, T>,
+    ) -> Result<(Vec<ImageLine<'a, T>>, Option<LineEnding>), ApplyError> {
+        let mut image: Vec<_> = LineIter::new(base_image)
+            .map(ImageLine::Unpatched)
+            .collect();
+        let ending = self.line_ending.resolve(LineIter::new(base_image));
+
+        if creates_file(patch) && !image.is_empty() {
+            return Err(ApplyError(ApplyErrorKind::NotEmpty));
+        }
+
+        for (i, hunk) in patch.hunks().iter().enumerate() {
+            apply_hunk_to_image(&mut image, hunk, self.matcher(ending))
+                .map_err(|_| ApplyError(ApplyErrorKind::Hunk(i + 1)))?;
+        }
+
+        if deletes_file(patch) && !image.is_empty() {
+            return Err(ApplyError(ApplyErrorKind::NotConsumed));
+        }
+
+        Ok((image, ending))
+    }
+
+    fn apply_partial_impl<'a, 'b, T: Text + ?Sized>(
+        &self,
+        base_image: &'b T,
+        patch: &Patch<'a, T>,
+    ) -> (Vec<ImageLine<'b, T>>, Option<LineEnding>, Vec<Hunk<'a, T>>)
+    where
+        'a: 'b,
+    {
+        let mut image: Vec<_> = LineIter::new(base_image)
+            .map(ImageLine::Unpatched)
+            .collect();
+        let ending = self.line_ending.resolve(LineIter::new(base_image));
+        let rejected = if creates_file(patch) && !image.is_empty() {
+            patch.hunks().to_vec()
+        } else {
+            apply_hunks_partial(&mut image, patch.hunks(), self.matcher(ending))
+        };
+
+        (image, ending, rejected)
+    }
+}
+
+impl Default for ApplyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Apply a `Patch` to a base image
 ///
+/// A patch whose old file is `/dev/null` creates a file, so the base image must be empty. One
+/// whose new file is `/dev/null` deletes a file, so its hunks must remove every line of the base
+/// image.
+///
+/// If most lines of the base image end in CRLF, a hunk which doesn't match exactly is matched
+/// without regard to line endings and any lines it inserts are written with CRLF line endings,
+/// see [`LineEnding`]. Hunks which match exactly or change line endings themselves, like those
+/// of a patch created from the base image, are applied as they are.
+///
 /// ```
 /// use diffy::{apply, Patch};
 ///
@@ -89,63 +335,295 @@ impl<T: ?Sized> Clone for ImageLine<'_, T> {
 /// assert_eq!(apply(base_image, &patch).unwrap(), expected);
 /// ```
 pub fn apply(base_image: &str, patch: &Patch<'_, str>) -> Result<String, ApplyError> {
-    let mut image: Vec<_> = LineIter::new(base_image)
-        .map(ImageLine::Unpatched)
-        .collect();
-
-    for (i, hunk) in patch.hunks().iter().enumerate() {
-        apply_hunk(&mut image, hunk).map_err(|_| ApplyError(i + 1))?;
-    }
+    ApplyOptions::default().apply(base_image, patch)
+}
 
-    Ok(image.into_iter().map(ImageLine::into_inner).collect())
+/// Apply a `Patch` to a base image and check that it produces `expected`
+///
+/// This is meant for debugging patches: when the result differs, the error holds a patch from
+/// the actual result to the expected one.
+///
+/// ```
+/// use diffy::{create_patch, verify_apply, VerifyError};
+///
+/// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+/// assert!(verify_apply("a\nb\nc\n", &patch, "a\nB\nc\n").is_ok());
+///
+/// match verify_apply("a\nb\nc\n", &patch, "a\nb\nC\n") {
+///     Err(VerifyError::Mismatch(diff)) => assert_eq!(
+///         diff.to_string(),
+///         "--- actual\n+++ expected\n@@ -1,3 +1,3 @@\n a\n-B\n-c\n+b\n+C\n"
+///     ),
+///     _ => panic!("expected a mismatch"),
+/// }
+/// ```
+pub fn verify_apply(
+    base_image: &str,
+    patch: &Patch<'_, str>,
+    expected: &str,
+) -> Result<(), VerifyError> {
+    ApplyOptions::default().verify_apply(base_image, patch, expected)
 }
 
 /// Apply a non-utf8 `Patch` to a base image
 pub fn apply_bytes(base_image: &[u8], patch: &Patch<'_, [u8]>) -> Result<Vec<u8>, ApplyError> {
-    let mut image: Vec<_> = LineIter::new(base_image)
-        .map(ImageLine::Unpatched)
-        .collect();
+    ApplyOptions::default().apply_bytes(base_image, patch)
+}
+
+/// Apply as many hunks of a `Patch` as possible to a base image
+///
+/// Like `patch` writing a `.rej` file, hunks which can't be applied are skipped instead of
+/// failing the whole patch. Returns the patched image along with the hunks which were rejected.
+///
+/// ```
+/// use diffy::{apply_partial, Patch};
+///
+/// let s = "\
+/// --- a/file
+/// +++ b/file
+/// @@ -1,2 +1,2 @@
+///  a
+/// -b
+/// +B
+/// @@ -4,2 +4,2 @@
+///  x
+/// -y
+/// +Y
+/// ";
+///
+/// let patch = Patch::from_str(s).unwrap();
+/// let (image, rejected) = apply_partial("a\nb\nc\nd\ne\n", &patch);
+///
+/// assert_eq!(image, "a\nB\nc\nd\ne\n");
+/// assert_eq!(rejected, &patch.hunks()[1..]);
+/// ```
+pub fn apply_partial<'a>(base_image: &str, patch: &Patch<'a, str>) -> (String, Vec<Hunk<'a, str>>) {
+    ApplyOptions::default().apply_partial(base_image, patch)
+}
+
+/// Apply as many hunks of a non-utf8 `Patch` as possible to a base image
+pub fn apply_bytes_partial<'a>(
+    base_image: &[u8],
+    patch: &Patch<'a, [u8]>,
+) -> (Vec<u8>, Vec<Hunk<'a, [u8]>>) {
+    ApplyOptions::default().apply_bytes_partial(base_image, patch)
+}
+
+/// Apply a single `Hunk` in place to a buffer of lines, at the position in its old range shifted
+/// by `offset` lines
+///
+/// Each line of the buffer includes its line ending, like the lines of the hunk. Unlike
+/// [`apply`], the hunk isn't searched for: its context and deleted lines have to match the
+/// buffer exactly starting at line `hunk.old_range().start() + offset`, otherwise the buffer is
+/// left untouched. When applying several hunks of a patch in order, `offset` is the number of
+/// lines inserted minus the number of lines deleted by the hunks applied before.
+///
+/// ```
+/// use diffy::{apply_hunk, create_patch};
+///
+/// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+/// let mut lines: Vec<String> = vec!["a\n".into(), "b\n".into(), "c\n".into()];
+///
+/// apply_hunk(&mut lines, &patch.hunks()[0], 0).unwrap();
+/// assert_eq!(lines, ["a\n", "B\n", "c\n"]);
+///
+/// // The lines no longer match
+/// assert!(apply_hunk(&mut lines, &patch.hunks()[0], 0).is_err());
+/// ```
+pub fn apply_hunk(
+    lines: &mut Vec<String>,
+    hunk: &Hunk<'_, str>,
+    offset: isize,
+) -> Result<(), ApplyError> {
+    // `None` if the shifted position overflows, in which case the hunk's own start is reported
+    let position = isize::try_from(hunk.old_range().position())
+        .ok()
+        .and_then(|position| position.checked_add(offset));
+    let error = || {
+        let line = position
+            .and_then(|position| position.checked_add(1))
+            .unwrap_or_else(|| isize::try_from(hunk.old_range().start()).unwrap_or(isize::MAX));
+        ApplyError(ApplyErrorKind::HunkAt(line))
+    };
+
+    let len = pre_image_line_count(hunk.lines());
+    let range = position
+        .and_then(|position| usize::try_from(position).ok())
+        .and_then(|position| Some(position..position.checked_add(len)?))
+        .ok_or_else(error)?;
+    let image = lines.get(range.clone()).ok_or_else(error)?;
+    if !pre_image(hunk.lines()).zip(image).all(|(a, b)| a == b) {
+        return Err(error());
+    }
+
+    let post_image = hunk.lines().iter().filter_map(|line| match line {
+        Line::Context(l) | Line::Insert(l) => Some((*l).to_owned()),
+        Line::Delete(_) => None,
+    });
+    lines.splice(range, post_image);
 
-    for (i, hunk) in patch.hunks().iter().enumerate() {
-        apply_hunk(&mut image, hunk).map_err(|_| ApplyError(i + 1))?;
+    Ok(())
+}
+
+// Git and GNU diff mark patches creating or deleting a file by naming the missing file
+// `/dev/null`
+fn creates_file<T: Text + ?Sized>(patch: &Patch<'_, T>) -> bool {
+    patch.original().map(Text::as_bytes) == Some(b"/dev/null")
+}
+
+fn deletes_file<T: Text + ?Sized>(patch: &Patch<'_, T>) -> bool {
+    patch.modified().map(Text::as_bytes) == Some(b"/dev/null")
+}
+
+fn apply_hunks_partial<'a: 'b, 'b, T: Text + ?Sized>(
+    image: &mut Vec<ImageLine<'b, T>>,
+    hunks: &[Hunk<'a, T>],
+    matcher: LineMatcher,
+) -> Vec<Hunk<'a, T>> {
+    hunks
+        .iter()
+        .filter(|hunk| apply_hunk_to_image(image, hunk, matcher).is_err())
+        .cloned()
+        .collect()
+}
+
+// How the lines of a hunk are compared to the lines of the base image
+#[derive(Clone, Copy, Debug)]
+struct LineMatcher {
+    ending: Option<LineEnding>,
+    ignore_trailing_whitespace: bool,
+}
+
+impl LineMatcher {
+    const EXACT: Self = Self {
+        ending: None,
+        ignore_trailing_whitespace: false,
+    };
+
+    fn lines_match<T: Text + ?Sized>(self, a: &T, b: &T) -> bool {
+        if self.ignore_trailing_whitespace {
+            // Line endings are whitespace too, so this also covers `ending`
+            return trim_end(a.as_bytes()) == trim_end(b.as_bytes());
+        }
+        match self.ending {
+            Some(_) => without_cr(a.as_bytes()) == without_cr(b.as_bytes()),
+            None => a == b,
+        }
+    }
+}
+
+fn trim_end(line: &[u8]) -> &[u8] {
+    let len = line
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    &line[..len]
+}
+
+// Returns the line to write out and the line ending to use in place of its own, if any
+fn output_line<'a, T: Text + ?Sized>(
+    line: ImageLine<'a, T>,
+    ending: Option<LineEnding>,
+    base_ending: Option<LineEnding>,
+) -> (&'a T, Option<&'static str>) {
+    let ending = match line {
+        ImageLine::Patched(_) => ending,
+        ImageLine::Unpatched(_) | ImageLine::PatchedAsIs(_) => base_ending,
+    };
+    match ending {
+        Some(ending) => ending.convert(line.into_inner()),
+        None => (line.into_inner(), None),
     }
+}
 
-    Ok(image
-        .into_iter()
-        .flat_map(ImageLine::into_inner)
-        .copied()
-        .collect())
+fn image_into_string(
+    image: Vec<ImageLine<'_, str>>,
+    ending: Option<LineEnding>,
+    base_ending: Option<LineEnding>,
+) -> String {
+    let mut output = String::new();
+    for line in image {
+        let (line, ending) = output_line(line, ending, base_ending);
+        output.push_str(line);
+        output.push_str(ending.unwrap_or_default());
+    }
+    output
+}
+
+fn image_into_bytes(
+    image: Vec<ImageLine<'_, [u8]>>,
+    ending: Option<LineEnding>,
+    base_ending: Option<LineEnding>,
+) -> Vec<u8> {
+    let mut output = Vec::new();
+    for line in image {
+        let (line, ending) = output_line(line, ending, base_ending);
+        output.extend_from_slice(line);
+        output.extend_from_slice(ending.unwrap_or_default().as_bytes());
+    }
+    output
 }
 
-fn apply_hunk<'a, T: PartialEq + ?Sized>(
+fn apply_hunk_to_image<'a, T: Text + ?Sized>(
     image: &mut Vec<ImageLine<'a, T>>,
     hunk: &Hunk<'a, T>,
+    matcher: LineMatcher,
 ) -> Result<(), ()> {
-    // Find position
-    let pos = find_position(image, hunk).ok_or(())?;
+    // Find position, preferring one where the hunk matches exactly
+    let exact = find_position(image, hunk, LineMatcher::EXACT);
+    let pos = exact
+        .or_else(|| find_position(image, hunk, matcher))
+        .ok_or(())?;
+
+    // A hunk which matches exactly or changes line endings itself was made against lines with
+    // the same line endings as the base image, so its lines are kept as they are. Otherwise its
+    // inserted lines are given the line ending of the base image.
+    let as_is = exact.is_some() || changes_line_endings(hunk);
+
+    // Context lines may only match the base image loosely, so keep the base image's version of
+    // them
+    let mut base = image[pos..].iter().map(ImageLine::inner);
+    let lines: Vec<_> = hunk
+        .lines()
+        .iter()
+        .filter_map(|line| match line {
+            Line::Context(_) => base.next().map(ImageLine::PatchedAsIs),
+            Line::Delete(_) => base.next().and(None),
+            Line::Insert(l) if as_is => Some(ImageLine::PatchedAsIs(*l)),
+            Line::Insert(l) => Some(ImageLine::Patched(*l)),
+        })
+        .collect();
 
     // update image
-    image.splice(
-        pos..pos + pre_image_line_count(hunk.lines()),
-        post_image(hunk.lines()).map(ImageLine::Patched),
-    );
+    image.splice(pos..pos + pre_image_line_count(hunk.lines()), lines);
 
     Ok(())
 }
 
+// Returns whether the inserted lines of the hunk mostly have another line ending than the lines
+// it replaces, like those of a patch converting the line endings of a file
+fn changes_line_endings<T: Text + ?Sized>(hunk: &Hunk<'_, T>) -> bool {
+    let inserted = hunk.lines().iter().filter_map(|line| match line {
+        Line::Insert(l) => Some(*l),
+        Line::Context(_) | Line::Delete(_) => None,
+    });
+    LineEnding::Auto.resolve(pre_image(hunk.lines())) != LineEnding::Auto.resolve(inserted)
+}
+
 // Search in `image` for a palce to apply hunk.
 // This follows the general algorithm (minus fuzzy-matching context lines) described in GNU patch's
 // man page.
 //
 // It might be worth looking into other possible positions to apply the hunk to as described here:
 // https://neil.fraser.name/writing/patch/
-fn find_position<T: PartialEq + ?Sized>(
+fn find_position<T: Text + ?Sized>(
     image: &[ImageLine<T>],
     hunk: &Hunk<'_, T>,
+    matcher: LineMatcher,
 ) -> Option<usize> {
     // In order to avoid searching through positions which are out of bounds of the image,
     // clamp the starting position based on the length of the image
-    let pos = std::cmp::min(hunk.new_range().start().saturating_sub(1), image.len());
+    let pos = std::cmp::min(hunk.new_range().position(), image.len());
 
     // Create an iterator that starts with 'pos' and then interleaves
     // moving pos backward/foward by one.
@@ -154,20 +632,13 @@ fn find_position<T: PartialEq + ?Sized>(
 
     iter::once(pos)
         .chain(interleave(backward, forward))
-        .find(|&pos| match_fragment(image, hunk.lines(), pos))
+        .find(|&pos| match_fragment(image, hunk.lines(), pos, matcher))
 }
 
 fn pre_image_line_count<T: ?Sized>(lines: &[Line<'_, T>]) -> usize {
     pre_image(lines).count()
 }
 
-fn post_image<'a, 'b, T: ?Sized>(lines: &'b [Line<'a, T>]) -> impl Iterator<Item = &'a T> + 'b {
-    lines.iter().filter_map(|line| match line {
-        Line::Context(l) | Line::Insert(l) => Some(*l),
-        Line::Delete(_) => None,
-    })
-}
-
 fn pre_image<'a, 'b, T: ?Sized>(lines: &'b [Line<'a, T>]) -> impl Iterator<Item = &'a T> + 'b {
     lines.iter().filter_map(|line| match line {
         Line::Context(l) | Line::Delete(l) => Some(*l),
@@ -175,10 +646,11 @@ fn pre_image<'a, 'b, T: ?Sized>(lines: &'b [Line<'a, T>]) -> impl Iterator<Item
     })
 }
 
-fn match_fragment<T: PartialEq + ?Sized>(
+fn match_fragment<T: Text + ?Sized>(
     image: &[ImageLine<T>],
     lines: &[Line<'_, T>],
     pos: usize,
+    matcher: LineMatcher,
 ) -> bool {
     let len = pre_image_line_count(lines);
 
@@ -193,7 +665,9 @@ fn match_fragment<T: PartialEq + ?Sized>(
         return false;
     }
 
-    pre_image(lines).eq(image.iter().map(ImageLine::inner))
+    pre_image(lines)
+        .zip(image.iter().map(ImageLine::inner))
+        .all(|(a, b)| matcher.lines_match(a, b))
 }
 
 #[derive(Debug)]
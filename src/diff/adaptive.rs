@@ -0,0 +1,204 @@
+use super::{Diff, DiffOptions};
+use crate::{
+    range::DiffRange,
+    utils::{split_words, LineIter},
+};
+use std::time::Instant;
+
+// A changed region is compared by words if at least this fraction of it is unchanged by words
+const WORD_SIMILARITY: f64 = 0.5;
+// It's compared by characters instead if at least this fraction of the words which changed is
+// unchanged by characters
+const CHAR_SIMILARITY: f64 = 0.7;
+
+/// How finely a region of an adaptive diff was compared, see [`DiffOptions::diff_adaptive`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    /// The region is compared by whole lines
+    Line,
+    /// The region is compared by runs of whitespace and non-whitespace
+    Word,
+    /// The region is compared by characters
+    Char,
+}
+
+/// A region of an adaptive diff along with the granularity it was compared at, see
+/// [`DiffOptions::diff_adaptive`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdaptiveRegion<'a> {
+    granularity: Granularity,
+    diffs: Vec<Diff<'a, str>>,
+}
+
+impl<'a> AdaptiveRegion<'a> {
+    /// Returns the granularity the region was compared at
+    pub fn granularity(&self) -> Granularity {
+        self.granularity
+    }
+
+    /// Returns the differences within the region
+    pub fn diffs(&self) -> &[Diff<'a, str>] {
+        &self.diffs
+    }
+
+    fn lines(old: &'a str, new: &'a str) -> Self {
+        let mut diffs = Vec::new();
+        if !old.is_empty() {
+            diffs.push(Diff::Delete(old));
+        }
+        if !new.is_empty() {
+            diffs.push(Diff::Insert(new));
+        }
+        Self {
+            granularity: Granularity::Line,
+            diffs,
+        }
+    }
+}
+
+pub(super) fn diff_adaptive<'a>(
+    opts: &DiffOptions,
+    original: &'a str,
+    modified: &'a str,
+) -> Vec<AdaptiveRegion<'a>> {
+    let old_lines: Vec<&str> = LineIter::new(original).collect();
+    let new_lines: Vec<&str> = LineIter::new(modified).collect();
+    // All the diffs share the same deadline
+    let deadline = opts.deadline();
+
+    let mut regions = Vec::new();
+    let mut old_pos = 0;
+    let mut new_pos = 0;
+    // The length of the changed region seen since the last unchanged lines in either text
+    let mut changed = (0, 0);
+
+    for diff in opts.diff_slice_until(&old_lines, &new_lines, deadline).0 {
+        match diff {
+            DiffRange::Equal(range, _) => {
+                if changed != (0, 0) {
+                    let old = &original[old_pos..old_pos + changed.0];
+                    let new = &modified[new_pos..new_pos + changed.1];
+                    regions.push(refine(opts, old, new, deadline));
+                    old_pos += changed.0;
+                    new_pos += changed.1;
+                    changed = (0, 0);
+                }
+
+                let len = byte_len(range.as_slice());
+                regions.push(AdaptiveRegion {
+                    granularity: Granularity::Line,
+                    diffs: vec![Diff::Equal(&original[old_pos..old_pos + len])],
+                });
+                old_pos += len;
+                new_pos += len;
+            }
+            DiffRange::Delete(range) => changed.0 += byte_len(range.as_slice()),
+            DiffRange::Insert(range) => changed.1 += byte_len(range.as_slice()),
+        }
+    }
+
+    if changed != (0, 0) {
+        regions.push(refine(
+            opts,
+            &original[old_pos..],
+            &modified[new_pos..],
+            deadline,
+        ));
+    }
+
+    regions
+}
+
+// Picks the finest granularity at which `old` and `new` are still similar
+fn refine<'a>(
+    opts: &DiffOptions,
+    old: &'a str,
+    new: &'a str,
+    deadline: Option<Instant>,
+) -> AdaptiveRegion<'a> {
+    if old.is_empty() || new.is_empty() || !opts.diffs_by_chars(old.len() + new.len()) {
+        return AdaptiveRegion::lines(old, new);
+    }
+
+    let words = diff_words(opts, old, new, deadline);
+    let unchanged_words = unchanged_len(&words);
+    let len = old.len() + new.len();
+    if similarity(2 * unchanged_words, len) < WORD_SIMILARITY {
+        return AdaptiveRegion::lines(old, new);
+    }
+
+    // Only compare the part which changed by words, the rest is unchanged either way
+    let chars = opts.diff(old, new, deadline);
+    let unchanged_chars = unchanged_len(&chars).saturating_sub(unchanged_words);
+    if similarity(2 * unchanged_chars, len - 2 * unchanged_words) >= CHAR_SIMILARITY {
+        AdaptiveRegion {
+            granularity: Granularity::Char,
+            diffs: chars,
+        }
+    } else {
+        AdaptiveRegion {
+            granularity: Granularity::Word,
+            diffs: words,
+        }
+    }
+}
+
+fn diff_words<'a>(
+    opts: &DiffOptions,
+    old: &'a str,
+    new: &'a str,
+    deadline: Option<Instant>,
+) -> Vec<Diff<'a, str>> {
+    // Words are split on ascii whitespace so they fall on char boundaries
+    let old_words = split_words(old.as_bytes());
+    let new_words = split_words(new.as_bytes());
+
+    let mut old_pos = 0;
+    let mut new_pos = 0;
+    opts.diff_slice_until(&old_words, &new_words, deadline)
+        .0
+        .into_iter()
+        .map(|diff| match diff {
+            DiffRange::Equal(range, _) => {
+                let len = byte_len(range.as_slice());
+                let text = &old[old_pos..old_pos + len];
+                old_pos += len;
+                new_pos += len;
+                Diff::Equal(text)
+            }
+            DiffRange::Delete(range) => {
+                let len = byte_len(range.as_slice());
+                let text = &old[old_pos..old_pos + len];
+                old_pos += len;
+                Diff::Delete(text)
+            }
+            DiffRange::Insert(range) => {
+                let len = byte_len(range.as_slice());
+                let text = &new[new_pos..new_pos + len];
+                new_pos += len;
+                Diff::Insert(text)
+            }
+        })
+        .collect()
+}
+
+fn unchanged_len(diffs: &[Diff<'_, str>]) -> usize {
+    diffs
+        .iter()
+        .filter_map(|diff| match diff {
+            Diff::Equal(text) => Some(text.len()),
+            Diff::Delete(_) | Diff::Insert(_) => None,
+        })
+        .sum()
+}
+
+fn similarity(unchanged: usize, len: usize) -> f64 {
+    if len == 0 {
+        return 1.0;
+    }
+    unchanged as f64 / len as f64
+}
+
+fn byte_len<T: AsRef<[u8]>>(pieces: &[T]) -> usize {
+    pieces.iter().map(|piece| piece.as_ref().len()).sum()
+}
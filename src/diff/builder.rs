@@ -0,0 +1,86 @@
+use super::DiffOptions;
+use crate::patch::{Patch, PatchSet};
+
+/// Builds the [`PatchSet`] of several files, e.g. to diff a changeset
+///
+/// Each file is diffed as it's added and its patch is given the filenames `a/<path>` and
+/// `b/<path>`, the way git names them, so the set can be applied with `patch -p1` or
+/// `git apply`. An empty old text is taken to be a created file and named `/dev/null`, and so is
+/// an empty new text of a deleted file. Files which are unchanged are left out of the set unless
+/// [`include_unchanged`](DiffBuilder::include_unchanged) is set.
+///
+/// ```
+/// use diffy::DiffBuilder;
+///
+/// let patches = DiffBuilder::new()
+///     .add_file("src/lib.rs", "a\nb\n", "a\nc\n", 3)
+///     .add_file("README.md", "unchanged\n", "unchanged\n", 3)
+///     .build();
+///
+/// assert_eq!(patches.len(), 1);
+/// assert_eq!(
+///     patches.to_string(),
+///     "\
+/// --- a/src/lib.rs
+/// +++ b/src/lib.rs
+/// @@ -1,2 +1,2 @@
+///  a
+/// -b
+/// +c
+/// "
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct DiffBuilder<'a> {
+    patches: Vec<Patch<'a, str>>,
+    include_unchanged: bool,
+}
+
+impl<'a> DiffBuilder<'a> {
+    /// Construct a `DiffBuilder` without any files
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Diff the old and new text of the file at `path` with `context_len` lines of context and
+    /// add its patch to the set
+    pub fn add_file(mut self, path: &str, old: &'a str, new: &'a str, context_len: usize) -> Self {
+        let hunks = DiffOptions::new()
+            .set_context_len(context_len)
+            .create_patch(old, new)
+            .into_iter()
+            .collect();
+        let name = |prefix, text: &str, other: &str| {
+            if text.is_empty() && !other.is_empty() {
+                "/dev/null".to_owned()
+            } else {
+                format!("{}/{}", prefix, path)
+            }
+        };
+        self.patches.push(Patch::new(
+            Some(name("a", old, new)),
+            Some(name("b", new, old)),
+            hunks,
+        ));
+        self
+    }
+
+    /// Include the patches of unchanged files, which only consist of the filename headers
+    ///
+    /// By default unchanged files are left out of the set.
+    pub fn include_unchanged(mut self, include_unchanged: bool) -> Self {
+        self.include_unchanged = include_unchanged;
+        self
+    }
+
+    /// Returns the set of patches of the added files, in the order they were added
+    pub fn build(self) -> PatchSet<'a> {
+        let include_unchanged = self.include_unchanged;
+        PatchSet::new(
+            self.patches
+                .into_iter()
+                .filter(|patch| include_unchanged || !patch.hunks().is_empty())
+                .collect(),
+        )
+    }
+}
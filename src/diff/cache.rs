@@ -0,0 +1,112 @@
+use super::DiffOptions;
+use crate::patch::OwnedPatch;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// A cache of the patches between pairs of texts, for when the same texts are diffed repeatedly
+///
+/// Texts are identified by a 64-bit hash of their content together with their length, so the
+/// texts themselves aren't kept around. Once the cache holds `capacity` patches, the least
+/// recently used one is evicted to make room for a new one.
+///
+/// ```
+/// use diffy::DiffCache;
+///
+/// let mut cache = DiffCache::new(16);
+/// let patch = cache.get_or_compute("a\nb\n", "a\nc\n", 3).to_string();
+///
+/// // The second time around the patch comes from the cache
+/// assert_eq!(cache.get_or_compute("a\nb\n", "a\nc\n", 3).to_string(), patch);
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct DiffCache {
+    capacity: usize,
+    entries: HashMap<Key, Entry>,
+    // Incremented on every lookup to track which entry was used least recently
+    clock: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Key {
+    old: (u64, usize),
+    new: (u64, usize),
+    context_len: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    patch: OwnedPatch,
+    last_used: u64,
+}
+
+impl DiffCache {
+    /// Construct an empty `DiffCache` holding at most `capacity` patches
+    ///
+    /// A capacity of 0 is treated as 1.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the patch between `old` and `new` with `context_len` lines of context, creating
+    /// and caching it if it isn't cached yet
+    pub fn get_or_compute(&mut self, old: &str, new: &str, context_len: usize) -> &OwnedPatch {
+        let key = Key {
+            old: fingerprint(old),
+            new: fingerprint(new),
+            context_len,
+        };
+        self.clock += 1;
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict_least_recently_used();
+        }
+        let entry = self.entries.entry(key).or_insert_with(|| Entry {
+            patch: DiffOptions::new()
+                .set_context_len(context_len)
+                .create_patch(old, new)
+                .into_owned(),
+            last_used: 0,
+        });
+        entry.last_used = self.clock;
+        &entry.patch
+    }
+
+    /// Returns the number of cached patches
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no patches are cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all cached patches
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+fn fingerprint(text: &str) -> (u64, usize) {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    (hasher.finish(), text.len())
+}
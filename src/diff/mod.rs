@@ -1,22 +1,39 @@
 use crate::{
     patch::{Hunk, HunkRange, Line, Patch},
-    range::{DiffRange, SliceLike},
-    utils::Classifier,
+    range::{DiffRange, Range, SliceLike},
+    utils::{without_cr, Classifier, LineEnding, LineIter, Text},
+};
+use std::{
+    borrow::Cow,
+    cmp,
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    ops,
+    time::{Duration, Instant},
 };
-use std::{cmp, ops};
 
+mod adaptive;
+mod builder;
+mod cache;
 mod cleanup;
 mod myers;
 
+pub use adaptive::{AdaptiveRegion, Granularity};
+pub use builder::DiffBuilder;
+pub use cache::DiffCache;
+
 #[cfg(test)]
 mod tests;
 
-// TODO determine if this should be exposed in the public API
-#[allow(dead_code)]
+/// A piece of a diff between two texts, see [`DiffOptions::diff_adaptive`]
 #[derive(Debug, PartialEq, Eq)]
-enum Diff<'a, T: ?Sized> {
+pub enum Diff<'a, T: ?Sized> {
+    /// Text present in both texts
     Equal(&'a T),
+    /// Text deleted from the old text
     Delete(&'a T),
+    /// Text inserted to the new text
     Insert(&'a T),
 }
 
@@ -41,22 +58,176 @@ where
     }
 }
 
+impl Diff<'_, str> {
+    /// Copies the text of the diff into an [`OwnedDiff`], which doesn't borrow from the texts it
+    /// was computed from
+    pub fn to_owned(&self) -> OwnedDiff {
+        match *self {
+            Diff::Equal(text) => OwnedDiff::Equal(text.to_owned()),
+            Diff::Delete(text) => OwnedDiff::Delete(text.to_owned()),
+            Diff::Insert(text) => OwnedDiff::Insert(text.to_owned()),
+        }
+    }
+}
+
+/// A [`Diff`] which owns its text instead of borrowing it from the texts it was computed from,
+/// so it can outlive them or be sent to another thread
+///
+/// Created with [`Diff::to_owned`], or for a whole diff with
+/// [`from_diffs`](OwnedDiff::from_diffs). Use [`as_diff`](OwnedDiff::as_diff) to get a `Diff`
+/// borrowing from it again.
+///
+/// ```
+/// use diffy::{Diff, DiffOptions, OwnedDiff};
+///
+/// let original = String::from("hello world\n");
+/// let modified = String::from("hello there\n");
+/// let regions = DiffOptions::new().diff_adaptive(&original, &modified);
+/// let owned = OwnedDiff::from_diffs(regions[0].diffs());
+/// drop(regions);
+/// drop((original, modified));
+///
+/// let diffs: Vec<Diff<'_, str>> = owned.iter().map(OwnedDiff::as_diff).collect();
+/// assert_eq!(
+///     diffs,
+///     [
+///         Diff::Equal("hello "),
+///         Diff::Delete("world"),
+///         Diff::Insert("there"),
+///         Diff::Equal("\n"),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OwnedDiff {
+    /// Text present in both texts
+    Equal(String),
+    /// Text deleted from the old text
+    Delete(String),
+    /// Text inserted to the new text
+    Insert(String),
+}
+
+impl OwnedDiff {
+    /// Copies the text of every diff of `diffs`, keeping their order
+    pub fn from_diffs(diffs: &[Diff<'_, str>]) -> Vec<OwnedDiff> {
+        diffs.iter().map(Diff::to_owned).collect()
+    }
+
+    /// Returns a `Diff` borrowing the text of this one
+    pub fn as_diff(&self) -> Diff<'_, str> {
+        match self {
+            OwnedDiff::Equal(text) => Diff::Equal(text),
+            OwnedDiff::Delete(text) => Diff::Delete(text),
+            OwnedDiff::Insert(text) => Diff::Insert(text),
+        }
+    }
+}
+
+impl From<Diff<'_, str>> for OwnedDiff {
+    fn from(diff: Diff<'_, str>) -> Self {
+        diff.to_owned()
+    }
+}
+
+/// Walks `diffs` along with the index of the first element each one covers in the old and the
+/// new input, which is a byte offset for diffs of texts
+///
+/// Equal and deleted elements advance the old index, equal and inserted ones the new index, so
+/// the indices of a diff are where the previous ones left off in either input.
+///
+/// ```
+/// use diffy::{indexed, Diff, DiffOptions};
+///
+/// let regions = DiffOptions::new().diff_adaptive("hello world\n", "hello there\n");
+/// let indices: Vec<_> = indexed(regions[0].diffs()).collect();
+/// assert_eq!(
+///     indices,
+///     [
+///         (0, 0, &Diff::Equal("hello ")),
+///         (6, 6, &Diff::Delete("world")),
+///         (11, 6, &Diff::Insert("there")),
+///         (11, 11, &Diff::Equal("\n")),
+///     ]
+/// );
+/// ```
+pub fn indexed<'a, 'b, T: ?Sized + SliceLike>(
+    diffs: &'b [Diff<'a, T>],
+) -> impl Iterator<Item = (usize, usize, &'b Diff<'a, T>)> {
+    diffs.iter().scan((0, 0), |(old, new), diff| {
+        let indices = (*old, *new, diff);
+        match diff {
+            Diff::Equal(s) => {
+                *old += s.len();
+                *new += s.len();
+            }
+            Diff::Delete(s) => *old += s.len(),
+            Diff::Insert(s) => *new += s.len(),
+        }
+        Some(indices)
+    })
+}
+
 /// A collection of options for modifying the way a diff is performed
+///
+/// ## Output
+///
+/// When there are multiple shortest edit scripts between two texts the one produced is
+/// deterministic and only depends on the input:
+/// * the alignment is the one found by the middle snake search of Myers' algorithm, which
+///   prefers extending paths along deletions over insertions when both reach equally far;
+/// * each run of deleted or inserted lines is then slid as far down as it can go over identical
+///   lines, merging it with neighboring changes where possible;
+/// * within a change, deleted lines are always emitted before inserted lines.
 #[derive(Debug)]
 pub struct DiffOptions {
     compact: bool,
     context_len: usize,
+    #[cfg(feature = "regex")]
+    ignore_matching_lines: Option<regex::Regex>,
+    normalizer: Option<fn(&str) -> Cow<'_, str>>,
+    ignore_space_change: bool,
+    #[cfg(feature = "experimental")]
+    match_indentation: bool,
+    comment_prefixes: Vec<String>,
+    line_ending: Option<LineEnding>,
+    timeout: Option<Duration>,
+    minimal: bool,
+    max_input_bytes: Option<usize>,
+    max_char_diff_bytes: usize,
+    ignore_whitespace_only_hunks: bool,
 }
 
+// The id all comment lines are classified as. The `Classifier` hands out ids counting up from 0
+// so it never reaches this one.
+const COMMENT_LINE_ID: u64 = u64::MAX;
+
+const DEFAULT_MAX_CHAR_DIFF_BYTES: usize = 1 << 14;
+
 impl DiffOptions {
     /// Construct a new `DiffOptions` with default settings
     ///
     /// ## Defaults
     /// * context_len = 3
+    /// * minimal = true
+    /// * max_char_diff_bytes = 16 KiB
     pub fn new() -> Self {
         Self {
             compact: true,
             context_len: 3,
+            #[cfg(feature = "regex")]
+            ignore_matching_lines: None,
+            normalizer: None,
+            ignore_space_change: false,
+            #[cfg(feature = "experimental")]
+            match_indentation: false,
+            comment_prefixes: Vec::new(),
+            line_ending: None,
+            timeout: None,
+            minimal: true,
+            max_input_bytes: None,
+            max_char_diff_bytes: DEFAULT_MAX_CHAR_DIFF_BYTES,
+            ignore_whitespace_only_hunks: false,
         }
     }
 
@@ -66,6 +237,185 @@ impl DiffOptions {
         self
     }
 
+    /// Ignore hunks whose inserted and deleted lines all match `regex`, similar to `diff -I`.
+    ///
+    /// A hunk containing at least one change which doesn't match is kept whole. Lines are
+    /// matched without their trailing newline and lines from non-utf8 texts never match.
+    #[cfg(feature = "regex")]
+    pub fn set_ignore_matching_lines(&mut self, regex: regex::Regex) -> &mut Self {
+        self.ignore_matching_lines = Some(regex);
+        self
+    }
+
+    /// Ignore hunks whose changes only add, remove or change whitespace, see
+    /// [`Hunk::is_whitespace_only`]
+    ///
+    /// Unlike [`set_ignore_space_change`](DiffOptions::set_ignore_space_change), which compares
+    /// lines with whitespace collapsed, this checks the hunks after the diff, so a hunk with any
+    /// other change is kept whole, whitespace changes included. Use
+    /// [`create_patch_counting_hidden`](DiffOptions::create_patch_counting_hidden) to find out
+    /// how many changes were dropped.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    /// let modified = "a\n  b\nc\nd\ne\nf\ng\nh\nI\n";
+    ///
+    /// let patch = DiffOptions::new()
+    ///     .set_context_len(1)
+    ///     .set_ignore_whitespace_only_hunks(true)
+    ///     .create_patch(original, modified);
+    /// assert_eq!(patch.to_string(), "--- original\n+++ modified\n@@ -8,2 +8,2 @@\n h\n-i\n+I\n");
+    /// ```
+    pub fn set_ignore_whitespace_only_hunks(&mut self, ignore: bool) -> &mut Self {
+        self.ignore_whitespace_only_hunks = ignore;
+        self
+    }
+
+    /// Compare lines by the result of `normalizer` instead of by their content.
+    ///
+    /// Lines which normalize to the same text are treated as equal, e.g. a normalizer which
+    /// lowercases lines makes the diff ignore changes in case. `normalizer` is given each line
+    /// including its trailing newline and must be deterministic. Lines from non-utf8 texts are
+    /// compared by their content.
+    ///
+    /// The patch still contains the original lines, with context lines taken from the modified
+    /// text, so it may not apply cleanly to the original text if normalization hid a change.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    /// use std::borrow::Cow;
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_normalizer(|line| Cow::Owned(line.trim().to_lowercase()));
+    ///
+    /// let patch = opts.create_patch("Hello\nWorld\n", "hello  \nWORLD\n");
+    /// assert!(patch.hunks().is_empty());
+    /// ```
+    pub fn set_normalizer(&mut self, normalizer: fn(&str) -> Cow<'_, str>) -> &mut Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Ignore changes in the amount of whitespace, similar to `diff -b`.
+    ///
+    /// Runs of whitespace (spaces, tabs, form feeds, vertical tabs and carriage returns) compare
+    /// equal to a single space regardless of how they are made up, and whitespace at the end of
+    /// a line is ignored. Adding whitespace where there was none is still a change. When a
+    /// normalizer is also set this is applied to its output.
+    pub fn set_ignore_space_change(&mut self, ignore_space_change: bool) -> &mut Self {
+        self.ignore_space_change = ignore_space_change;
+        self
+    }
+
+    /// Only match lines which are indented to the same depth.
+    ///
+    /// The width of each line's leading whitespace, with tabs advancing to the next multiple of
+    /// 8 columns, becomes part of what is compared. Whitespace-only lines have no depth. This
+    /// keeps a block which was moved to another nesting level from being matched when
+    /// [`set_ignore_space_change`](DiffOptions::set_ignore_space_change) or a normalizer would
+    /// otherwise hide the change in indentation.
+    ///
+    /// This is an experimental heuristic which is only available with the `experimental`
+    /// feature.
+    #[cfg(feature = "experimental")]
+    pub fn set_match_indentation(&mut self, match_indentation: bool) -> &mut Self {
+        self.match_indentation = match_indentation;
+        self
+    }
+
+    /// Ignore changes to comment lines, i.e. lines which start with any of `prefixes` after
+    /// leading whitespace.
+    ///
+    /// All comment lines compare equal to each other, so rewording a comment doesn't produce a
+    /// hunk while adding or removing comment lines still does. Comment lines still appear in the
+    /// patch as context.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_ignore_comment_lines(vec!["//".to_owned(), "#".to_owned()]);
+    ///
+    /// let patch = opts.create_patch("// one\nx = 1\n", "  // uno\nx = 1\n");
+    /// assert!(patch.hunks().is_empty());
+    /// ```
+    pub fn set_ignore_comment_lines(&mut self, prefixes: Vec<String>) -> &mut Self {
+        self.comment_prefixes = prefixes;
+        self
+    }
+
+    /// Compare lines regardless of whether they end in `\r\n` or `\n`, see [`LineEnding`]
+    ///
+    /// The original text decides whether to do so when set to `LineEnding::Auto`. By default
+    /// line endings are compared exactly, so that a patch can change them. Since the patch
+    /// contains the lines as they are, the choice between `Lf` and `CrLf` doesn't matter here.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) -> &mut Self {
+        self.line_ending = Some(line_ending);
+        self
+    }
+
+    /// Limit the time spent searching for the differences between two texts
+    ///
+    /// The deadline is checked once every 64 rounds of the search, each of which looks for edit
+    /// scripts one edit longer than the last, so a diff can run somewhat over the limit. Once
+    /// the limit is reached, the parts of the texts which are left to diff are treated as
+    /// entirely deleted and inserted. The result is still a correct patch, only a coarser one.
+    /// Use [`try_create_patch`](DiffOptions::try_create_patch) to find out if that happened.
+    ///
+    /// By default there is no limit.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Choose between always finding a minimal diff and giving up on it for speed, similar to
+    /// git's `--minimal`.
+    ///
+    /// A minimal diff has the fewest possible inserted and deleted lines, which takes time
+    /// proportional to the size of the texts times the number of changes. When this is turned
+    /// off, the search for each part of the diff is cut short after a number of steps growing
+    /// with the square root of the texts' size, settling for the best split found so far. This
+    /// bounds the time spent on very different texts, but the patch may change more lines than
+    /// needed. It only makes a difference for texts with at least a few hundred changes.
+    ///
+    /// By default diffs are minimal.
+    pub fn set_minimal(&mut self, minimal: bool) -> &mut Self {
+        self.minimal = minimal;
+        self
+    }
+
+    /// Limit the combined size of the two texts, in bytes, for guarding against untrusted input
+    ///
+    /// The memory needed for a diff grows with the size of the texts, so the size is checked
+    /// before anything is allocated. The `checked_*` methods, like
+    /// [`checked_create_patch`](DiffOptions::checked_create_patch), refuse larger texts. The
+    /// methods comparing changed lines by characters, like
+    /// [`text_edits`](DiffOptions::text_edits), compare larger texts by lines first instead, and
+    /// replace runs of changed lines which are still larger whole.
+    ///
+    /// By default there is no limit.
+    pub fn set_max_input_bytes(&mut self, max_input_bytes: usize) -> &mut Self {
+        self.max_input_bytes = Some(max_input_bytes);
+        self
+    }
+
+    /// Limit the size, in bytes, of changed lines which are compared by characters or words
+    ///
+    /// Comparing text by characters takes time proportional to its length times the number of
+    /// changes, so a single changed line of a minified file could take very long. Runs of changed
+    /// lines which are longer than this, counting both texts, are replaced whole instead. This
+    /// applies to [`text_edits`](DiffOptions::text_edits),
+    /// [`edit_ops`](DiffOptions::edit_ops) and [`diff_adaptive`](DiffOptions::diff_adaptive).
+    /// Line-level diffs compare each line as a whole, however long, so they aren't affected.
+    ///
+    /// By default the limit is 16 KiB.
+    pub fn set_max_char_diff_bytes(&mut self, max_char_diff_bytes: usize) -> &mut Self {
+        self.max_char_diff_bytes = max_char_diff_bytes;
+        self
+    }
+
     /// Enable/Disable diff compaction. Compaction is a post-processing step which attempts to
     /// produce a prettier diff by reducing the number of edited blocks by shifting and merging
     /// edit blocks.
@@ -77,9 +427,18 @@ impl DiffOptions {
     }
 
     // TODO determine if this should be exposed in the public API
-    #[allow(dead_code)]
-    fn diff<'a>(&self, original: &'a str, modified: &'a str) -> Vec<Diff<'a, str>> {
-        let solution = myers::diff(original.as_bytes(), modified.as_bytes());
+    fn diff<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+        deadline: Option<Instant>,
+    ) -> Vec<Diff<'a, str>> {
+        let (solution, _) = myers::diff_with_limits(
+            original.as_bytes(),
+            modified.as_bytes(),
+            deadline,
+            self.minimal,
+        );
 
         let mut solution = solution
             .into_iter()
@@ -93,46 +452,864 @@ impl DiffOptions {
         solution.into_iter().map(Diff::from).collect()
     }
 
+    /// Compute the character-level changes between two texts as replacements of byte ranges of
+    /// `original`
+    ///
+    /// Each run of deleted and inserted text between unchanged text becomes a single
+    /// [`TextEdit`], as used by document synchronization protocols. The edits are in order and
+    /// don't overlap, so applying them from last to first leaves the ranges of the remaining
+    /// ones valid. Ranges always fall on char boundaries.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "The quick brown fox";
+    /// let modified = "The slow brown dog";
+    ///
+    /// let mut text = original.to_owned();
+    /// for edit in DiffOptions::new().text_edits(original, modified).iter().rev() {
+    ///     text.replace_range(edit.range(), edit.new_text());
+    /// }
+    /// assert_eq!(text, modified);
+    /// ```
+    pub fn text_edits(&self, original: &str, modified: &str) -> Vec<TextEdit> {
+        self.changes(original, modified)
+            .into_iter()
+            .map(|(old, new)| TextEdit {
+                range: old,
+                new_text: modified[new].to_owned(),
+            })
+            .collect()
+    }
+
+    /// Compute the differences between two texts, comparing each changed region as finely as
+    /// keeps it readable
+    ///
+    /// The texts are compared by lines first. Each run of changed lines between unchanged ones is
+    /// then compared again by words if most of it is unchanged at that granularity, and by
+    /// characters instead if the words which changed are still mostly the same, e.g. when a typo
+    /// was fixed. Otherwise the run is reported as deleted and inserted lines. Unchanged lines
+    /// always form a region of their own.
+    ///
+    /// ```
+    /// use diffy::{Diff, DiffOptions, Granularity};
+    ///
+    /// let original = "Call me Ishmael.\nSome years ago, never mind how long precisely.\n";
+    /// let modified = "Call me Ishmael.\nSome years ago, never mind how long exactly.\n";
+    ///
+    /// let regions = DiffOptions::new().diff_adaptive(original, modified);
+    /// assert_eq!(regions[0].granularity(), Granularity::Line);
+    /// assert_eq!(regions[0].diffs(), [Diff::Equal("Call me Ishmael.\n")]);
+    /// assert_eq!(regions[1].granularity(), Granularity::Word);
+    /// assert_eq!(
+    ///     regions[1].diffs(),
+    ///     [
+    ///         Diff::Equal("Some years ago, never mind how long "),
+    ///         Diff::Delete("precisely."),
+    ///         Diff::Insert("exactly."),
+    ///         Diff::Equal("\n"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff_adaptive<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> Vec<AdaptiveRegion<'a>> {
+        adaptive::diff_adaptive(self, original, modified)
+    }
+
+    /// Compute the character-level changes between two texts as insertions and deletions at
+    /// byte positions of `original`, as consumed by operational transformation systems
+    ///
+    /// Each run of deleted or inserted text becomes an [`EditOp`], widened to whole chars.
+    /// Positions are in the coordinates of `original`: a deletion starts where the deleted text
+    /// starts and an insertion is at the position in `original` it's inserted at. The ops are in
+    /// order and a deletion comes before the insertion replacing it, so applying them from first
+    /// to last only needs to shift positions by the length changes of the previous ops.
+    ///
+    /// ```
+    /// use diffy::{DiffOptions, EditOp};
+    ///
+    /// let ops = DiffOptions::new().edit_ops("The quick fox", "The fox jumps");
+    /// assert_eq!(
+    ///     ops,
+    ///     [
+    ///         EditOp::Delete { position: 4, len: 6 },
+    ///         EditOp::Insert { position: 13, text: " jumps" },
+    ///     ]
+    /// );
+    /// ```
+    pub fn edit_ops<'a>(&self, original: &str, modified: &'a str) -> Vec<EditOp<'a>> {
+        let mut ops = Vec::new();
+        for (old, new) in self.changes(original, modified) {
+            if !old.is_empty() {
+                ops.push(EditOp::Delete {
+                    position: old.start,
+                    len: old.len(),
+                });
+            }
+            if !new.is_empty() {
+                ops.push(EditOp::Insert {
+                    position: old.end,
+                    text: &modified[new],
+                });
+            }
+        }
+        ops
+    }
+
+    // The runs of changed bytes of `original` and `modified` along with the bytes replacing them,
+    // widened to whole chars
+    fn changes(
+        &self,
+        original: &str,
+        modified: &str,
+    ) -> Vec<(ops::Range<usize>, ops::Range<usize>)> {
+        let deadline = self.deadline();
+
+        // The texts are compared by bytes, so a char may be split across several changes
+        let mut changes = Vec::new();
+        let mut pos = (0, 0);
+        if self.diffs_by_chars(original.len() + modified.len()) {
+            let (solution, _) =
+                self.diff_slice_until(original.as_bytes(), modified.as_bytes(), deadline);
+            push_byte_changes(&mut changes, &mut pos, &solution);
+        } else {
+            // Compare by lines first, and only compare runs of changed lines by bytes if they're
+            // small enough
+            let old_lines: Vec<&str> = LineIter::new(original).collect();
+            let new_lines: Vec<&str> = LineIter::new(modified).collect();
+            let (solution, _) = self.diff_slice_until(&old_lines, &new_lines, deadline);
+            let mut changed = (0, 0);
+            for diff in solution.iter().map(Some).chain([None]) {
+                let equal = match diff {
+                    Some(DiffRange::Delete(range)) => {
+                        changed.0 += lines_len(range.as_slice());
+                        continue;
+                    }
+                    Some(DiffRange::Insert(range)) => {
+                        changed.1 += lines_len(range.as_slice());
+                        continue;
+                    }
+                    Some(DiffRange::Equal(range, _)) => lines_len(range.as_slice()),
+                    None => 0,
+                };
+
+                if self.diffs_by_chars(changed.0 + changed.1) {
+                    let old = &original.as_bytes()[pos.0..pos.0 + changed.0];
+                    let new = &modified.as_bytes()[pos.1..pos.1 + changed.1];
+                    let (solution, _) = self.diff_slice_until(old, new, deadline);
+                    push_byte_changes(&mut changes, &mut pos, &solution);
+                } else {
+                    push_change(&mut changes, &mut pos, changed);
+                }
+                changed = (0, 0);
+                pos.0 += equal;
+                pos.1 += equal;
+            }
+        }
+
+        // Widen each change to char boundaries through the equal bytes around it, merging it
+        // with the previous change if it runs into it
+        let at_boundary = |old_pos, new_pos| {
+            original.is_char_boundary(old_pos) && modified.is_char_boundary(new_pos)
+        };
+        let mut widened: Vec<(ops::Range<usize>, ops::Range<usize>)> = Vec::new();
+        for i in 0..changes.len() {
+            let (mut old, mut new) = changes[i].clone();
+
+            let floor = widened.last().map_or(0, |(old, _)| old.end);
+            while !at_boundary(old.start, new.start) && old.start > floor {
+                old.start -= 1;
+                new.start -= 1;
+            }
+            let ceiling = changes
+                .get(i + 1)
+                .map_or(original.len(), |(old, _)| old.start);
+            while !at_boundary(old.end, new.end) && old.end < ceiling {
+                old.end += 1;
+                new.end += 1;
+            }
+
+            match widened.last_mut() {
+                Some((prev_old, prev_new)) if prev_old.end == old.start => {
+                    prev_old.end = old.end;
+                    prev_new.end = new.end;
+                }
+                _ => widened.push((old, new)),
+            }
+        }
+
+        widened
+    }
+
     /// Produce a Patch between two texts based on the configured options
+    ///
+    /// Identical texts are checked for up front, returning a patch without any hunks without
+    /// running the diff, so this is cheap to call on texts which are likely to be unchanged.
     pub fn create_patch<'a>(&self, original: &'a str, modified: &'a str) -> Patch<'a, str> {
+        self.create_patch_impl(original, modified).0
+    }
+
+    /// Produce a Patch between two texts along with the number of changes hidden by the options
+    /// which ignore some differences, e.g. to let a user know that whitespace changes were
+    /// hidden
+    ///
+    /// A change is a run of deleted and inserted lines in the diff comparing lines exactly. It
+    /// counts as hidden if none of its lines are changed by the patch, whether because the
+    /// options made its lines compare equal or because its hunk was dropped.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb  c\nd\ne\nf\ng\nh\n";
+    /// let modified = "a\nb c\nd\ne\nf\ng\nH\n";
+    ///
+    /// let (patch, hidden) = DiffOptions::new()
+    ///     .set_ignore_space_change(true)
+    ///     .create_patch_counting_hidden(original, modified);
+    /// assert_eq!(patch.hunks().len(), 1);
+    /// assert_eq!(hidden, 1);
+    /// ```
+    pub fn create_patch_counting_hidden<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> (Patch<'a, str>, usize) {
+        let patch = self.create_patch(original, modified);
+        if original == modified {
+            return (patch, 0);
+        }
+
+        let ((_, old_ids), (_, new_ids)) = DiffOptions::new().classify_texts(original, modified);
+        let exact = build_edit_script(&self.diff_slice(&old_ids, &new_ids));
+
+        // The lines of the old text deleted by the patch and the positions it inserts at
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        for hunk in patch.hunks() {
+            let mut old_line = hunk.old_range().position();
+            for line in hunk.lines() {
+                match line {
+                    Line::Context(_) => old_line += 1,
+                    Line::Delete(_) => {
+                        deleted.push(old_line);
+                        old_line += 1;
+                    }
+                    Line::Insert(_) => inserted.push(old_line),
+                }
+            }
+        }
+
+        let hidden = exact
+            .iter()
+            .filter(|edit| {
+                !deleted.iter().any(|line| edit.old.contains(line))
+                    && !inserted
+                        .iter()
+                        .any(|&line| edit.old.start <= line && line <= edit.old.end)
+            })
+            .count();
+        (patch, hidden)
+    }
+
+    /// Compute the lines covered by each hunk of the patch between two texts, without building
+    /// the patch
+    ///
+    /// Each pair holds the 0-based range of lines of `original` and of `modified` spanned by a
+    /// hunk, context included, as given by the hunk headers of [`create_patch`]. This is all a
+    /// change indicator like a minimap needs.
+    ///
+    /// [`create_patch`]: DiffOptions::create_patch
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let ranges = DiffOptions::new()
+    ///     .set_context_len(1)
+    ///     .hunk_line_ranges("a\nb\nc\nd\ne\nf\n", "a\nB\nc\nd\ne\nF\n");
+    /// assert_eq!(ranges, [(0..3, 0..3), (4..6, 4..6)]);
+    /// ```
+    pub fn hunk_line_ranges(
+        &self,
+        original: &str,
+        modified: &str,
+    ) -> Vec<(ops::Range<usize>, ops::Range<usize>)> {
+        // Which hunks are ignored depends on their content
+        if self.filters_hunks() {
+            return self
+                .create_patch(original, modified)
+                .hunks()
+                .iter()
+                .map(|hunk| {
+                    let (old, new) = (hunk.old_range(), hunk.new_range());
+                    (
+                        old.position()..old.position() + old.len(),
+                        new.position()..new.position() + new.len(),
+                    )
+                })
+                .collect();
+        }
+
+        if original == modified {
+            return Vec::new();
+        }
+
+        let ((old_lines, old_ids), (new_lines, new_ids)) = self.classify_texts(original, modified);
+        let script = build_edit_script(&self.diff_slice(&old_ids, &new_ids));
+
+        hunk_spans(old_lines.len(), new_lines.len(), &script, self.context_len)
+            .into_iter()
+            .map(|span| (span.old, span.new))
+            .collect()
+    }
+
+    /// Produce a Patch between two texts, returning an error holding a coarser patch if the
+    /// [timeout](DiffOptions::set_timeout) was reached
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    /// use std::time::Duration;
+    ///
+    /// let original = "a\nb\nc\nd\ne\n";
+    /// let modified = "a\nB\nc\nD\ne\n";
+    ///
+    /// let patch = DiffOptions::new()
+    ///     .set_timeout(Duration::from_secs(1))
+    ///     .try_create_patch(original, modified)
+    ///     .unwrap();
+    /// assert_eq!(patch.stats().deletions(), 2);
+    ///
+    /// // Without any time to spare, everything between the first and last change is replaced
+    /// let timeout = DiffOptions::new()
+    ///     .set_timeout(Duration::ZERO)
+    ///     .try_create_patch(original, modified)
+    ///     .unwrap_err();
+    /// assert_eq!(timeout.patch().stats().deletions(), 3);
+    /// ```
+    pub fn try_create_patch<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> Result<Patch<'a, str>, Timeout<'a>> {
+        match self.create_patch_impl(original, modified) {
+            (patch, false) => Ok(patch),
+            (patch, true) => Err(Timeout(patch)),
+        }
+    }
+
+    fn create_patch_impl<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> (Patch<'a, str>, bool) {
+        if original == modified {
+            return (
+                Patch::new(Some("original"), Some("modified"), Vec::new()),
+                false,
+            );
+        }
+
+        let ((old_lines, old_ids), (new_lines, new_ids)) = self.classify_texts(original, modified);
+
+        let (solution, timed_out) = self.diff_slice_with_timeout(&old_ids, &new_ids);
+
+        let hunks = to_hunks(&old_lines, &new_lines, &solution, self.context_len);
+        let hunks = self.filter_hunks(hunks);
+        (
+            Patch::new(Some("original"), Some("modified"), hunks),
+            timed_out,
+        )
+    }
+
+    /// Produce a Patch between two texts, keeping the given pairs of lines aligned
+    ///
+    /// Each hint is a pair of 0-based line indices into `original` and `modified` which are
+    /// known to correspond, e.g. from ids embedded in the lines. Hinted lines are kept as
+    /// unchanged lines and the texts in between are diffed separately, so changes never move
+    /// across a hint. A [timeout](DiffOptions::set_timeout) limits the time spent on all of them
+    /// together.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "[entry]\nid = 1\n[entry]\nid = 2\n";
+    /// let modified = "[entry]\nid = 2\n";
+    /// let opts = DiffOptions::new();
+    ///
+    /// // Align the headers of the entries with the same id
+    /// let patch = opts.create_patch_with_hints(original, modified, &[(2, 0)]).unwrap();
+    /// assert_eq!(
+    ///     patch.to_string(),
+    ///     "--- original\n+++ modified\n@@ -1,4 +1,2 @@\n-[entry]\n-id = 1\n [entry]\n id = 2\n"
+    /// );
+    /// ```
+    ///
+    /// Returns an error if the hints aren't strictly increasing in both texts, refer to lines
+    /// which don't exist, or pair lines with different content.
+    pub fn create_patch_with_hints<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+        hints: &[(usize, usize)],
+    ) -> Result<Patch<'a, str>, HintError> {
+        let ((old_lines, old_ids), (new_lines, new_ids)) = self.classify_texts(original, modified);
+
+        // All the texts between hints share the same deadline
+        let deadline = self.deadline();
+        let mut solution = Vec::new();
+        let mut start = (0, 0);
+        for (i, &(old, new)) in hints.iter().enumerate() {
+            if old < start.0
+                || new < start.1
+                || old >= old_ids.len()
+                || new >= new_ids.len()
+                || old_ids[old] != new_ids[new]
+            {
+                return Err(HintError(i));
+            }
+
+            let (between, _) =
+                self.diff_slice_until(&old_ids[start.0..old], &new_ids[start.1..new], deadline);
+            solution.extend(between);
+            solution.push(DiffRange::Equal(
+                Range::new(&old_ids[..], old..old + 1),
+                Range::new(&new_ids[..], new..new + 1),
+            ));
+            start = (old + 1, new + 1);
+        }
+        let (rest, _) = self.diff_slice_until(&old_ids[start.0..], &new_ids[start.1..], deadline);
+        solution.extend(rest);
+
+        let hunks = to_hunks(&old_lines, &new_lines, &solution, self.context_len);
+        let hunks = self.filter_hunks(hunks);
+        Ok(Patch::new(Some("original"), Some("modified"), hunks))
+    }
+
+    /// Produce every line of the two texts, rather than hunks, based on the configured options
+    ///
+    /// Unchanged lines are returned as context, taken from `modified`, and changed lines as
+    /// deletions followed by insertions, in the same order they'd appear in a patch with
+    /// unlimited context.
+    ///
+    /// ```
+    /// use diffy::{DiffOptions, Line};
+    ///
+    /// let lines = DiffOptions::new().diff_lines("a\nb\nc\n", "a\nB\nc\n");
+    /// assert_eq!(
+    ///     lines,
+    ///     [
+    ///         Line::Context("a\n"),
+    ///         Line::Delete("b\n"),
+    ///         Line::Insert("B\n"),
+    ///         Line::Context("c\n"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff_lines<'a>(&self, original: &'a str, modified: &'a str) -> Vec<Line<'a, str>> {
+        self.diff_lines_impl(original, modified)
+    }
+
+    /// Produce every line of two potentially non-utf8 texts, rather than hunks
+    pub fn diff_lines_bytes<'a>(
+        &self,
+        original: &'a [u8],
+        modified: &'a [u8],
+    ) -> Vec<Line<'a, [u8]>> {
+        self.diff_lines_impl(original, modified)
+    }
+
+    fn diff_lines_impl<'a, T: ?Sized + Text>(
+        &self,
+        original: &'a T,
+        modified: &'a T,
+    ) -> Vec<Line<'a, T>> {
+        let ((old_lines, old_ids), (new_lines, new_ids)) = self.classify_texts(original, modified);
+
+        let solution = self.diff_slice(&old_ids, &new_ids);
+
+        let mut lines = Vec::with_capacity(old_lines.len().max(new_lines.len()));
+        let mut new_idx = 0;
+        for script in build_edit_script(&solution) {
+            lines.extend(
+                new_lines[new_idx..script.new.start]
+                    .iter()
+                    .map(|l| Line::Context(*l)),
+            );
+            lines.extend(old_lines[script.old].iter().map(|l| Line::Delete(*l)));
+            lines.extend(
+                new_lines[script.new.clone()]
+                    .iter()
+                    .map(|l| Line::Insert(*l)),
+            );
+            new_idx = script.new.end;
+        }
+        lines.extend(new_lines[new_idx..].iter().map(|l| Line::Context(*l)));
+        lines
+    }
+
+    /// Produce a Patch between two texts which were already split into lines
+    ///
+    /// The lines are used as given instead of splitting the texts at each `\n`, so they should
+    /// keep their line terminators as with [`str::split_inclusive`]. A line without a trailing
+    /// `\n` is rendered as missing the newline at the end of the file.
+    ///
+    /// ```
+    /// use diffy::{create_patch, DiffOptions};
+    ///
+    /// let original = "a\nb\nc\n";
+    /// let modified = "a\nB\nc\n";
+    /// let old_lines: Vec<_> = original.split_inclusive('\n').collect();
+    /// let new_lines: Vec<_> = modified.split_inclusive('\n').collect();
+    ///
+    /// let patch = DiffOptions::new().create_patch_from_lines(&old_lines, &new_lines);
+    /// assert_eq!(patch, create_patch(original, modified));
+    /// ```
+    pub fn create_patch_from_lines<'a>(
+        &self,
+        original: &[&'a str],
+        modified: &[&'a str],
+    ) -> Patch<'a, str> {
+        let ignore_cr = self
+            .line_ending
+            .and_then(|ending| ending.resolve(original.iter().copied()))
+            .is_some();
         let mut classifier = Classifier::default();
-        let (old_lines, old_ids) = classifier.classify_lines(original);
-        let (new_lines, new_ids) = classifier.classify_lines(modified);
+        let (old_lines, old_ids) =
+            self.classify_lines(&mut classifier, original.iter().copied(), ignore_cr);
+        let (new_lines, new_ids) =
+            self.classify_lines(&mut classifier, modified.iter().copied(), ignore_cr);
 
         let solution = self.diff_slice(&old_ids, &new_ids);
 
         let hunks = to_hunks(&old_lines, &new_lines, &solution, self.context_len);
+        let hunks = self.filter_hunks(hunks);
+        Patch::new(Some("original"), Some("modified"), hunks)
+    }
+
+    /// Create a patch from an edit script computed elsewhere
+    ///
+    /// Each [`EditRange`] replaces a range of lines of `original`, counted from 0, with a range
+    /// of lines of `modified`. Unchanged lines are taken from `modified` and aren't checked
+    /// against `original`.
+    ///
+    /// ```
+    /// use diffy::{DiffOptions, EditRange};
+    ///
+    /// let original = "a\nb\nc\n";
+    /// let modified = "a\nB\nc\nd\n";
+    /// let script = [EditRange::new(1..2, 1..2), EditRange::new(3..3, 3..4)];
+    ///
+    /// let patch = DiffOptions::new().create_patch_from_edit_script(original, modified, &script);
+    /// assert_eq!(patch, diffy::create_patch(original, modified));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edit is empty or out of bounds, if the edits aren't in order and separated
+    /// by at least one unchanged line, or if the number of unchanged lines differs between the
+    /// two texts.
+    pub fn create_patch_from_edit_script<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+        script: &[EditRange],
+    ) -> Patch<'a, str> {
+        let old_lines: Vec<_> = LineIter::new(original).collect();
+        let new_lines: Vec<_> = LineIter::new(modified).collect();
+        assert_valid_edit_script(script, old_lines.len(), new_lines.len());
+
+        let hunks = script_to_hunks(&old_lines, &new_lines, script, self.context_len);
+        let hunks = self.filter_hunks(hunks);
         Patch::new(Some("original"), Some("modified"), hunks)
     }
 
+    /// Produce a Patch between two texts, returning an error instead if they are larger than
+    /// the [limit](DiffOptions::set_max_input_bytes)
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_max_input_bytes(8);
+    /// assert!(opts.checked_create_patch("a\nb\n", "a\nc\n").is_ok());
+    ///
+    /// let error = opts.checked_create_patch("a\nb\nc\n", "a\nc\n").unwrap_err();
+    /// assert_eq!(error.input_bytes(), 10);
+    /// assert_eq!(error.to_string(), "diff input of 10 bytes exceeds the limit of 8 bytes");
+    /// ```
+    pub fn checked_create_patch<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> Result<Patch<'a, str>, InputTooLarge> {
+        self.check_input_bytes(original.len(), modified.len())?;
+        Ok(self.create_patch(original, modified))
+    }
+
+    /// Produce a Patch between two potentially non-utf8 texts, returning an error instead if
+    /// they are larger than the [limit](DiffOptions::set_max_input_bytes)
+    pub fn checked_create_patch_bytes<'a>(
+        &self,
+        original: &'a [u8],
+        modified: &'a [u8],
+    ) -> Result<Patch<'a, [u8]>, InputTooLarge> {
+        self.check_input_bytes(original.len(), modified.len())?;
+        Ok(self.create_patch_bytes(original, modified))
+    }
+
+    fn check_input_bytes(&self, old_len: usize, new_len: usize) -> Result<(), InputTooLarge> {
+        let input_bytes = old_len.saturating_add(new_len);
+        match self.max_input_bytes {
+            Some(max_input_bytes) if input_bytes > max_input_bytes => Err(InputTooLarge {
+                input_bytes,
+                max_input_bytes,
+            }),
+            _ => Ok(()),
+        }
+    }
+
     /// Create a patch between two potentially non-utf8 texts
     pub fn create_patch_bytes<'a>(
         &self,
         original: &'a [u8],
         modified: &'a [u8],
     ) -> Patch<'a, [u8]> {
-        let mut classifier = Classifier::default();
-        let (old_lines, old_ids) = classifier.classify_lines(original);
-        let (new_lines, new_ids) = classifier.classify_lines(modified);
+        if original == modified {
+            return Patch::new(Some(&b"original"[..]), Some(&b"modified"[..]), Vec::new());
+        }
+
+        let ((old_lines, old_ids), (new_lines, new_ids)) = self.classify_texts(original, modified);
 
         let solution = self.diff_slice(&old_ids, &new_ids);
 
         let hunks = to_hunks(&old_lines, &new_lines, &solution, self.context_len);
+        let hunks = self.filter_hunks(hunks);
         Patch::new(Some(&b"original"[..]), Some(&b"modified"[..]), hunks)
     }
 
+    /// Count the lines deleted from and inserted into `original` without producing a Patch
+    ///
+    /// Returns `(deletions, insertions)`. Lines are counted before any hunks are dropped by
+    /// other options.
+    pub fn count_changed_lines(&self, original: &str, modified: &str) -> (usize, usize) {
+        self.count_changed_lines_impl(original, modified)
+    }
+
+    /// Count the lines deleted and inserted between two potentially non-utf8 texts
+    pub fn count_changed_lines_bytes(&self, original: &[u8], modified: &[u8]) -> (usize, usize) {
+        self.count_changed_lines_impl(original, modified)
+    }
+
+    fn count_changed_lines_impl<T: ?Sized + Text>(
+        &self,
+        original: &T,
+        modified: &T,
+    ) -> (usize, usize) {
+        if original == modified {
+            return (0, 0);
+        }
+
+        let ((_, old_ids), (_, new_ids)) = self.classify_texts(original, modified);
+
+        self.diff_slice(&old_ids, &new_ids)
+            .iter()
+            .fold((0, 0), |(deletions, insertions), diff| match diff {
+                DiffRange::Equal(..) => (deletions, insertions),
+                DiffRange::Delete(range) => (deletions + range.len(), insertions),
+                DiffRange::Insert(range) => (deletions, insertions + range.len()),
+            })
+    }
+
+    /// Returns the smallest context length for which all of the changes between the two texts
+    /// end up in a single hunk
+    pub fn context_len_to_merge_all(&self, original: &str, modified: &str) -> usize {
+        self.merge_thresholds(original, modified)
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the largest context length for which no two changes between the two texts end up
+    /// in the same hunk, or `None` if there are less than two changes
+    pub fn max_distinct_context_len(&self, original: &str, modified: &str) -> Option<usize> {
+        self.merge_thresholds(original, modified)
+            .into_iter()
+            .min()
+            .map(|threshold| threshold - 1)
+    }
+
+    // Returns, for each pair of consecutive changes, the smallest context length which merges
+    // them into the same hunk. This mirrors the merging logic in `to_hunks`.
+    fn merge_thresholds<T: ?Sized + Text>(&self, original: &T, modified: &T) -> Vec<usize> {
+        let ((old_lines, old_ids), (new_lines, new_ids)) = self.classify_texts(original, modified);
+        let solution = self.diff_slice(&old_ids, &new_ids);
+        let edit_script = build_edit_script(&solution);
+
+        edit_script
+            .windows(2)
+            .map(|pair| {
+                let (script, next) = (&pair[0], &pair[1]);
+                (1..)
+                    .find(|&context_len| {
+                        let (end1, _) = calc_end(
+                            context_len,
+                            old_lines.len(),
+                            new_lines.len(),
+                            script.old.end,
+                            script.new.end,
+                        );
+                        let start1_next = cmp::min(next.old.start, old_lines.len() - 1)
+                            .saturating_sub(context_len);
+                        start1_next < end1
+                    })
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    // Splits both texts into lines and classifies them with a shared classifier
+    #[allow(clippy::type_complexity)]
+    fn classify_texts<'a, T: ?Sized + Text>(
+        &self,
+        original: &'a T,
+        modified: &'a T,
+    ) -> ((Vec<&'a T>, Vec<u64>), (Vec<&'a T>, Vec<u64>)) {
+        let ignore_cr = self
+            .line_ending
+            .and_then(|ending| ending.resolve(original.lines()))
+            .is_some();
+        let mut classifier = Classifier::default();
+        (
+            self.classify_lines(&mut classifier, original.lines(), ignore_cr),
+            self.classify_lines(&mut classifier, modified.lines(), ignore_cr),
+        )
+    }
+
+    // Classifies `lines`, by their normalized form if a normalizer is set. Lines are keyed by
+    // their bytes so that str and [u8] texts share the same logic.
+    fn classify_lines<'a, T: ?Sized + Text>(
+        &self,
+        classifier: &mut Classifier<'a, [u8]>,
+        lines: impl IntoIterator<Item = &'a T>,
+        ignore_cr: bool,
+    ) -> (Vec<&'a T>, Vec<u64>) {
+        lines
+            .into_iter()
+            .map(|line| {
+                if self.is_comment_line(line.as_bytes()) {
+                    return (line, COMMENT_LINE_ID);
+                }
+
+                let key = match (self.normalizer, line.as_str()) {
+                    (Some(normalize), Some(line)) => match normalize(line) {
+                        Cow::Borrowed(key) => Cow::Borrowed(key.as_bytes()),
+                        Cow::Owned(key) => Cow::Owned(key.into_bytes()),
+                    },
+                    _ => Cow::Borrowed(line.as_bytes()),
+                };
+                let key = match key {
+                    Cow::Borrowed(key) if ignore_cr => without_cr(key),
+                    Cow::Owned(key) if ignore_cr => Cow::Owned(without_cr(&key).into_owned()),
+                    key => key,
+                };
+                let key = if self.ignore_space_change {
+                    Cow::Owned(collapse_whitespace(&key))
+                } else {
+                    key
+                };
+                #[cfg(feature = "experimental")]
+                let key = if self.match_indentation {
+                    let mut depth = indentation_width(line.as_bytes()).to_le_bytes().to_vec();
+                    depth.extend_from_slice(&key);
+                    Cow::Owned(depth)
+                } else {
+                    key
+                };
+                (line, classifier.classify_key(key))
+            })
+            .unzip()
+    }
+
+    fn is_comment_line(&self, line: &[u8]) -> bool {
+        let start = line
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(line.len());
+        self.comment_prefixes
+            .iter()
+            .any(|prefix| line[start..].starts_with(prefix.as_bytes()))
+    }
+
+    fn filter_hunks<'a, T>(&self, mut hunks: Vec<Hunk<'a, T>>) -> Vec<Hunk<'a, T>>
+    where
+        T: ?Sized + Text + AsRef<[u8]>,
+    {
+        #[cfg(feature = "regex")]
+        if let Some(regex) = &self.ignore_matching_lines {
+            hunks.retain(|hunk| !is_ignorable_hunk(hunk, regex));
+        }
+        if self.ignore_whitespace_only_hunks {
+            hunks.retain(|hunk| !hunk.is_whitespace_only());
+        }
+
+        hunks
+    }
+
+    // Whether `filter_hunks` may drop any hunks
+    fn filters_hunks(&self) -> bool {
+        #[cfg(feature = "regex")]
+        if self.ignore_matching_lines.is_some() {
+            return true;
+        }
+        self.ignore_whitespace_only_hunks
+    }
+
     pub(crate) fn diff_slice<'a, T: PartialEq>(
         &self,
         old: &'a [T],
         new: &'a [T],
     ) -> Vec<DiffRange<'a, 'a, [T]>> {
-        let mut solution = myers::diff(old, new);
+        self.diff_slice_with_timeout(old, new).0
+    }
+
+    // Also returns whether the timeout was reached
+    fn diff_slice_with_timeout<'a, T: PartialEq>(
+        &self,
+        old: &'a [T],
+        new: &'a [T],
+    ) -> (Vec<DiffRange<'a, 'a, [T]>>, bool) {
+        self.diff_slice_until(old, new, self.deadline())
+    }
+
+    // Like `diff_slice_with_timeout`, for one of several diffs sharing the same deadline
+    fn diff_slice_until<'a, T: PartialEq>(
+        &self,
+        old: &'a [T],
+        new: &'a [T],
+        deadline: Option<Instant>,
+    ) -> (Vec<DiffRange<'a, 'a, [T]>>, bool) {
+        let (mut solution, timed_out) = myers::diff_with_limits(old, new, deadline, self.minimal);
 
         if self.compact {
             cleanup::compact(&mut solution);
         }
 
-        solution
+        (solution, timed_out)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+
+    // Whether changed text of `len` bytes is compared by chars or words rather than replaced as
+    // whole lines
+    pub(crate) fn diffs_by_chars(&self, len: usize) -> bool {
+        len <= self.max_char_diff_bytes
+            && self
+                .max_input_bytes
+                .map_or(true, |max_input_bytes| len <= max_input_bytes)
     }
 }
 
@@ -145,7 +1322,7 @@ impl Default for DiffOptions {
 // TODO determine if this should be exposed in the public API
 #[allow(dead_code)]
 fn diff<'a>(original: &'a str, modified: &'a str) -> Vec<Diff<'a, str>> {
-    DiffOptions::default().diff(original, modified)
+    DiffOptions::default().diff(original, modified, None)
 }
 
 /// Create a patch between two texts.
@@ -190,6 +1367,110 @@ pub fn create_patch_bytes<'a>(original: &'a [u8], modified: &'a [u8]) -> Patch<'
     DiffOptions::default().create_patch_bytes(original, modified)
 }
 
+/// Find the longest common subsequence between two slices
+///
+/// The elements in the returned subsequence are borrowed from `old`.
+///
+/// ```
+/// # use diffy::lcs;
+/// let old = b"ABCABBA";
+/// let new = b"CBABAC";
+///
+/// let subsequence: Vec<u8> = lcs(old, new).into_iter().copied().collect();
+/// assert_eq!(subsequence, b"CBBA");
+/// ```
+pub fn lcs<'a, T: PartialEq>(old: &'a [T], new: &[T]) -> Vec<&'a T> {
+    myers::diff(old, new)
+        .into_iter()
+        .filter_map(|diff| match diff {
+            DiffRange::Equal(range, _) => Some(range.as_slice()),
+            DiffRange::Delete(_) | DiffRange::Insert(_) => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Find the longest common subsequence between two slices, comparing elements by a key
+///
+/// Each element is interned by the key returned from `key` and the resulting ids are diffed,
+/// so `T` itself doesn't need to be comparable. The elements in the returned subsequence are
+/// borrowed from `old`.
+///
+/// ```
+/// # use diffy::lcs_by_key;
+/// let old = [(1, "one"), (2, "two"), (3, "three")];
+/// let new = [(1, "uno"), (3, "tres")];
+///
+/// let subsequence = lcs_by_key(&old, &new, |&(id, _)| id);
+/// assert_eq!(subsequence, [&(1, "one"), &(3, "three")]);
+/// ```
+pub fn lcs_by_key<'a, T, K, F>(old: &'a [T], new: &[T], key: F) -> Vec<&'a T>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    let mut ids = HashMap::new();
+    let mut intern = |item: &T| {
+        let next_id = ids.len() as u64;
+        *ids.entry(key(item)).or_insert(next_id)
+    };
+    let old_ids: Vec<u64> = old.iter().map(&mut intern).collect();
+    let new_ids: Vec<u64> = new.iter().map(&mut intern).collect();
+
+    myers::diff(&old_ids, &new_ids)
+        .into_iter()
+        .filter_map(|diff| match diff {
+            DiffRange::Equal(range, _) => Some(&old[range.range()]),
+            DiffRange::Delete(_) | DiffRange::Insert(_) => None,
+        })
+        .flatten()
+        .collect()
+}
+
+// Adds the changes of a byte-level diff of the parts of the texts starting at `pos`
+fn push_byte_changes(
+    changes: &mut Vec<(ops::Range<usize>, ops::Range<usize>)>,
+    pos: &mut (usize, usize),
+    solution: &[DiffRange<'_, '_, [u8]>],
+) {
+    // The spans are relative to the diffed parts
+    let base = *pos;
+    for diff in solution {
+        let old_end = diff.old_span().map_or(pos.0, |span| base.0 + span.end);
+        let new_end = diff.new_span().map_or(pos.1, |span| base.1 + span.end);
+        if let DiffRange::Equal(..) = diff {
+            *pos = (old_end, new_end);
+        } else {
+            push_change(changes, pos, (old_end - pos.0, new_end - pos.1));
+        }
+    }
+}
+
+// Adds `changed.0` bytes of the old text replaced by `changed.1` bytes of the new text at `pos`,
+// extending the last change if it ends there
+fn push_change(
+    changes: &mut Vec<(ops::Range<usize>, ops::Range<usize>)>,
+    pos: &mut (usize, usize),
+    changed: (usize, usize),
+) {
+    if changed == (0, 0) {
+        return;
+    }
+    match changes.last() {
+        Some((old, new)) if (old.end, new.end) == *pos => {}
+        _ => changes.push((pos.0..pos.0, pos.1..pos.1)),
+    }
+    pos.0 += changed.0;
+    pos.1 += changed.1;
+    let (old, new) = changes.last_mut().unwrap();
+    old.end = pos.0;
+    new.end = pos.1;
+}
+
+fn lines_len(lines: &[&str]) -> usize {
+    lines.iter().map(|line| line.len()).sum()
+}
+
 fn to_hunks<'a, T: ?Sized>(
     lines1: &[&'a T],
     lines2: &[&'a T],
@@ -197,89 +1478,189 @@ fn to_hunks<'a, T: ?Sized>(
     context_len: usize,
 ) -> Vec<Hunk<'a, T>> {
     let edit_script = build_edit_script(solution);
+    script_to_hunks(lines1, lines2, &edit_script, context_len)
+}
 
-    let mut hunks = Vec::new();
+fn script_to_hunks<'a, T: ?Sized>(
+    lines1: &[&'a T],
+    lines2: &[&'a T],
+    edit_script: &[EditRange],
+    context_len: usize,
+) -> Vec<Hunk<'a, T>> {
+    hunk_spans(lines1.len(), lines2.len(), edit_script, context_len)
+        .into_iter()
+        .map(|span| {
+            let edits = &edit_script[span.edits];
+            let mut lines = Vec::new();
 
-    let mut idx = 0;
-    while let Some(mut script) = edit_script.get(idx) {
-        let start1 = script.old.start.saturating_sub(context_len);
-        let start2 = script.new.start.saturating_sub(context_len);
+            // Context lines only match up to what the options ignore, so a hunk which only
+            // deletes lines takes them from the old file in order to apply to it as is, while
+            // any other hunk takes them from the new file
+            let deletes_only = edits.iter().all(|script| script.new.is_empty());
+            let context = |old: ops::Range<usize>, new: ops::Range<usize>| {
+                let (context_lines, range) = if deletes_only {
+                    (lines1, old)
+                } else {
+                    (lines2, new)
+                };
+                context_lines.get(range).unwrap_or_default()
+            };
 
-        let (mut end1, mut end2) = calc_end(
-            context_len,
-            lines1.len(),
-            lines2.len(),
-            script.old.end,
-            script.new.end,
-        );
+            // Pre-context
+            for line in context(
+                span.old.start..edits[0].old.start,
+                span.new.start..edits[0].new.start,
+            ) {
+                lines.push(Line::Context(*line));
+            }
 
-        let mut lines = Vec::new();
+            for (i, script) in edits.iter().enumerate() {
+                // Delete lines from text1
+                for line in lines1.get(script.old.clone()).into_iter().flatten() {
+                    lines.push(Line::Delete(*line));
+                }
 
-        // Pre-context
-        for line in lines2.get(start2..script.new.start).into_iter().flatten() {
-            lines.push(Line::Context(*line));
-        }
+                // Insert lines from text2
+                for line in lines2.get(script.new.clone()).into_iter().flatten() {
+                    lines.push(Line::Insert(*line));
+                }
 
-        loop {
-            // Delete lines from text1
-            for line in lines1.get(script.old.clone()).into_iter().flatten() {
-                lines.push(Line::Delete(*line));
+                // Context lines between the edits of the hunk, or post-context after the last
+                let (old_end, new_end) = edits
+                    .get(i + 1)
+                    .map_or((span.old.end, span.new.end), |s| (s.old.start, s.new.start));
+                for line in context(script.old.end..old_end, script.new.end..new_end) {
+                    lines.push(Line::Context(*line));
+                }
             }
 
-            // Insert lines from text2
-            for line in lines2.get(script.new.clone()).into_iter().flatten() {
-                lines.push(Line::Insert(*line));
-            }
+            let len1 = span.old.len();
+            let old_range = HunkRange::new(
+                if len1 > 0 {
+                    span.old.start + 1
+                } else {
+                    span.old.start
+                },
+                len1,
+            );
 
-            if let Some(s) = edit_script.get(idx + 1) {
-                // Check to see if we can merge the hunks
-                let start1_next =
-                    cmp::min(s.old.start, lines1.len() - 1).saturating_sub(context_len);
-                if start1_next < end1 {
-                    // Context lines between hunks
-                    for (_i1, i2) in (script.old.end..s.old.start).zip(script.new.end..s.new.start)
-                    {
-                        if let Some(line) = lines2.get(i2) {
-                            lines.push(Line::Context(*line));
-                        }
-                    }
+            let len2 = span.new.len();
+            let new_range = HunkRange::new(
+                if len2 > 0 {
+                    span.new.start + 1
+                } else {
+                    span.new.start
+                },
+                len2,
+            );
 
-                    // Calc the new end
-                    let (e1, e2) = calc_end(
-                        context_len,
-                        lines1.len(),
-                        lines2.len(),
-                        s.old.end,
-                        s.new.end,
-                    );
-
-                    end1 = e1;
-                    end2 = e2;
-                    script = s;
-                    idx += 1;
-                    continue;
-                }
+            Hunk::new(old_range, new_range, None, lines)
+        })
+        .collect()
+}
+
+// The lines covered by a hunk, including context, and the edits it's made of
+#[derive(Debug)]
+struct HunkSpan {
+    old: ops::Range<usize>,
+    new: ops::Range<usize>,
+    edits: ops::Range<usize>,
+}
+
+// Groups the edits of `edit_script` into hunks, merging edits whose context would overlap
+fn hunk_spans(
+    len1: usize,
+    len2: usize,
+    edit_script: &[EditRange],
+    context_len: usize,
+) -> Vec<HunkSpan> {
+    let mut spans = Vec::new();
+
+    let mut idx = 0;
+    while let Some(script) = edit_script.get(idx) {
+        let first = idx;
+        let start1 = script.old.start.saturating_sub(context_len);
+        let start2 = script.new.start.saturating_sub(context_len);
+
+        let (mut end1, mut end2) =
+            calc_end(context_len, len1, len2, script.old.end, script.new.end);
+
+        // Check to see if we can merge the following hunks
+        while let Some(s) = edit_script.get(idx + 1) {
+            let start1_next = cmp::min(s.old.start, len1 - 1).saturating_sub(context_len);
+            if start1_next >= end1 {
+                break;
             }
 
-            break;
+            let (e1, e2) = calc_end(context_len, len1, len2, s.old.end, s.new.end);
+            end1 = e1;
+            end2 = e2;
+            idx += 1;
         }
 
-        // Post-context
-        for line in lines2.get(script.new.end..end2).into_iter().flatten() {
-            lines.push(Line::Context(*line));
+        spans.push(HunkSpan {
+            old: start1..end1,
+            new: start2..end2,
+            edits: first..idx + 1,
+        });
+        idx += 1;
+    }
+
+    spans
+}
+
+// A hunk can be ignored if every inserted or deleted line matches the regex
+#[cfg(feature = "regex")]
+fn is_ignorable_hunk<T: ?Sized + Text>(hunk: &Hunk<'_, T>, regex: &regex::Regex) -> bool {
+    hunk.lines().iter().all(|line| match line {
+        Line::Context(_) => true,
+        Line::Delete(line) | Line::Insert(line) => {
+            let line = line.strip_suffix("\n").unwrap_or(line);
+            line.as_str().map_or(false, |line| regex.is_match(line))
         }
+    })
+}
 
-        let len1 = end1 - start1;
-        let old_range = HunkRange::new(if len1 > 0 { start1 + 1 } else { start1 }, len1);
+#[cfg(feature = "experimental")]
+fn indentation_width(line: &[u8]) -> usize {
+    let mut width = 0;
+    for &byte in line {
+        match byte {
+            b' ' => width += 1,
+            b'\t' => width += 8 - width % 8,
+            b'\r' | b'\n' => return 0,
+            _ => return width,
+        }
+    }
+    0
+}
 
-        let len2 = end2 - start2;
-        let new_range = HunkRange::new(if len2 > 0 { start2 + 1 } else { start2 }, len2);
+// Collapses runs of whitespace into a single space and drops trailing whitespace, keeping the
+// line's terminating newline
+fn collapse_whitespace(line: &[u8]) -> Vec<u8> {
+    let (body, newline) = match line.strip_suffix(b"\n") {
+        Some(body) => (body, true),
+        None => (line, false),
+    };
 
-        hunks.push(Hunk::new(old_range, new_range, None, lines));
-        idx += 1;
+    let mut collapsed = Vec::with_capacity(line.len());
+    let mut in_whitespace = false;
+    for &byte in body {
+        if matches!(byte, b' ' | b'\t' | b'\x0b' | b'\x0c' | b'\r') {
+            in_whitespace = true;
+        } else {
+            if in_whitespace {
+                collapsed.push(b' ');
+                in_whitespace = false;
+            }
+            collapsed.push(byte);
+        }
+    }
+    if newline {
+        collapsed.push(b'\n');
     }
 
-    hunks
+    collapsed
 }
 
 fn calc_end(
@@ -303,16 +1684,193 @@ fn calc_end(
     (end1, end2)
 }
 
+/// An error returned by [`DiffOptions::create_patch_with_hints`] for an invalid hint
 #[derive(Debug)]
-struct EditRange {
+pub struct HintError(usize);
+
+impl HintError {
+    /// Returns the index of the offending hint
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for HintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "hint at index {} is out of order, out of bounds or pairs differing lines",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for HintError {}
+
+/// An error returned by [`DiffOptions::try_create_patch`] when the timeout was reached
+///
+/// It holds the patch produced anyway, which is correct but may replace more lines than needed.
+#[derive(Debug)]
+pub struct Timeout<'a>(Patch<'a, str>);
+
+impl<'a> Timeout<'a> {
+    /// Returns the coarser patch produced after the timeout was reached
+    pub fn patch(&self) -> &Patch<'a, str> {
+        &self.0
+    }
+
+    /// Converts the error into the coarser patch produced after the timeout was reached
+    pub fn into_patch(self) -> Patch<'a, str> {
+        self.0
+    }
+}
+
+impl fmt::Display for Timeout<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "diff timed out")
+    }
+}
+
+impl std::error::Error for Timeout<'_> {}
+
+/// An error returned by the `checked_*` methods of [`DiffOptions`] when the texts are larger
+/// than the [limit](DiffOptions::set_max_input_bytes)
+#[derive(Debug)]
+pub struct InputTooLarge {
+    input_bytes: usize,
+    max_input_bytes: usize,
+}
+
+impl InputTooLarge {
+    /// Returns the combined size of the two texts, in bytes
+    pub fn input_bytes(&self) -> usize {
+        self.input_bytes
+    }
+
+    /// Returns the limit which was exceeded
+    pub fn max_input_bytes(&self) -> usize {
+        self.max_input_bytes
+    }
+}
+
+impl fmt::Display for InputTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diff input of {} bytes exceeds the limit of {} bytes",
+            self.input_bytes, self.max_input_bytes
+        )
+    }
+}
+
+impl std::error::Error for InputTooLarge {}
+
+/// A replacement of a byte range of the old text, see [`DiffOptions::text_edits`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    range: ops::Range<usize>,
+    new_text: String,
+}
+
+impl TextEdit {
+    /// Returns the range of bytes of the old text which is replaced, which is empty for an
+    /// insertion
+    pub fn range(&self) -> ops::Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the text replacing the range, which is empty for a deletion
+    pub fn new_text(&self) -> &str {
+        &self.new_text
+    }
+}
+
+/// An insertion into or a deletion from the old text, see [`DiffOptions::edit_ops`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditOp<'a> {
+    /// Insert `text` at byte `position` of the old text
+    Insert {
+        /// The byte offset in the old text
+        position: usize,
+        /// The inserted text
+        text: &'a str,
+    },
+    /// Delete `len` bytes starting at byte `position` of the old text
+    Delete {
+        /// The byte offset in the old text
+        position: usize,
+        /// The number of deleted bytes
+        len: usize,
+    },
+}
+
+/// A range of lines in the old text which is replaced by a range of lines in the new text
+///
+/// Line numbers are counted from 0. Either range may be empty, but not both.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EditRange {
     old: ops::Range<usize>,
     new: ops::Range<usize>,
 }
 
 impl EditRange {
-    fn new(old: ops::Range<usize>, new: ops::Range<usize>) -> Self {
+    /// Creates an edit replacing the `old` lines with the `new` lines
+    pub fn new(old: ops::Range<usize>, new: ops::Range<usize>) -> Self {
         Self { old, new }
     }
+
+    /// Returns the range of replaced lines in the old text
+    pub fn old_range(&self) -> ops::Range<usize> {
+        self.old.clone()
+    }
+
+    /// Returns the range of replacing lines in the new text
+    pub fn new_range(&self) -> ops::Range<usize> {
+        self.new.clone()
+    }
+}
+
+fn assert_valid_edit_script(script: &[EditRange], old_len: usize, new_len: usize) {
+    let mut unchanged = (0, 0);
+    for edit in script {
+        assert!(
+            edit.old.start <= edit.old.end && edit.new.start <= edit.new.end,
+            "edit {:?} has a reversed range",
+            edit
+        );
+        assert!(
+            edit.old.end <= old_len && edit.new.end <= new_len,
+            "edit {:?} is out of bounds",
+            edit
+        );
+        assert!(
+            !edit.old.is_empty() || !edit.new.is_empty(),
+            "edit {:?} is empty",
+            edit
+        );
+        assert!(
+            unchanged.0 <= edit.old.start && unchanged.1 <= edit.new.start,
+            "edit {:?} is out of order",
+            edit
+        );
+        assert_eq!(
+            edit.old.start - unchanged.0,
+            edit.new.start - unchanged.1,
+            "edit {:?} is preceded by a different number of unchanged lines in each text",
+            edit
+        );
+        assert!(
+            unchanged == (0, 0) || edit.old.start > unchanged.0,
+            "edit {:?} isn't separated from the previous edit",
+            edit
+        );
+        unchanged = (edit.old.end, edit.new.end);
+    }
+    assert_eq!(
+        old_len - unchanged.0,
+        new_len - unchanged.1,
+        "the edit script is followed by a different number of unchanged lines in each text"
+    );
 }
 
 fn build_edit_script<T>(solution: &[DiffRange<[T]>]) -> Vec<EditRange> {
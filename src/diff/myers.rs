@@ -1,5 +1,26 @@
 use crate::range::{DiffRange, Range};
-use std::ops::{Index, IndexMut};
+use std::{
+    cmp,
+    ops::{Index, IndexMut},
+    time::Instant,
+};
+
+// The number of iterations of the search for a middle snake between checks of the deadline
+const DEADLINE_CHECK_INTERVAL: isize = 64;
+
+// The fewest iterations the search for a middle snake is given before it gives up when not
+// looking for a minimal diff, the same as `XDL_MAX_COST_MIN` in git's xdiff
+const MAX_COST_MIN: usize = 256;
+
+// Bounds on the work put into a diff
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    // Once passed, the ranges which are left to diff are replaced wholesale
+    deadline: Option<Instant>,
+    // The number of iterations after which the search for a middle snake settles for splitting
+    // the range at the furthest reaching forward path instead, or `None` to always find it
+    max_cost: Option<isize>,
+}
 
 // A D-path is a path which starts at (0,0) that has exactly D non-diagonal edges. All D-paths
 // consist of a (D - 1)-path followed by a non-diagonal edge and then a possibly empty sequence of
@@ -77,12 +98,17 @@ fn max_d(len1: usize, len2: usize) -> usize {
 // D-path. The idea for doing so is to simultaneously run the basic algorithm in both the
 // forward and reverse directions until furthest reaching forward and reverse paths starting at
 // opposing corners 'overlap'.
+//
+// Returns `None` if the deadline passes before the middle snake is found. If the maximum cost is
+// reached first, an empty snake at the end of the furthest reaching forward path is returned
+// instead, which still splits the range but may not lie on a shortest edit script.
 fn find_middle_snake<T: PartialEq>(
     old: Range<'_, [T]>,
     new: Range<'_, [T]>,
     vf: &mut V,
     vb: &mut V,
-) -> (isize, Snake) {
+    limits: Limits,
+) -> Option<(isize, Snake)> {
     let n = old.len();
     let m = new.len();
 
@@ -91,6 +117,13 @@ fn find_middle_snake<T: PartialEq>(
     let delta = n as isize - m as isize;
     let odd = delta & 1 == 1;
 
+    // `vf` and `vb` are shared by every call made while conquering a diff so they still hold
+    // values from previous calls. Those are never read: iteration `d` only reads the entries for
+    // diagonals `-(d - 1)..=(d - 1)`, all of which were written by iteration `d - 1` of this
+    // call, and the overlap checks only read entries of the same parity which were written
+    // earlier in this call. The only exception is iteration `0`, which reads the entries seeded
+    // here.
+    //
     // The initial point at (0, -1)
     vf[1] = 0;
     // The initial point at (N, M+1)
@@ -102,8 +135,24 @@ fn find_middle_snake<T: PartialEq>(
     assert!(vb.len() >= d_max);
 
     for d in 0..d_max as isize {
+        if d % DEADLINE_CHECK_INTERVAL == 0
+            && limits
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            return None;
+        }
+
+        if limits.max_cost.map_or(false, |max_cost| d >= max_cost) {
+            if let Some(snake) = furthest_forward_point(vf, d - 1, n, m) {
+                return Some((d, snake));
+            }
+        }
+
         // Forward path
         for k in (-d..=d).rev().step_by(2) {
+            // Move down (an insertion) only if it reaches strictly further, ties are broken in
+            // favor of moving right (a deletion)
             let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
                 vf[k + 1]
             } else {
@@ -135,7 +184,7 @@ fn find_middle_snake<T: PartialEq>(
                         y_end: y,
                     };
                     // Edit distance to this snake is `2 * d - 1`
-                    return (2 * d - 1, snake);
+                    return Some((2 * d - 1, snake));
                 }
             }
         }
@@ -171,24 +220,44 @@ fn find_middle_snake<T: PartialEq>(
                         y_end: m - y0,
                     };
                     // Edit distance to this snake is `2 * d`
-                    return (2 * d, snake);
+                    return Some((2 * d, snake));
                 }
             }
         }
-
-        // TODO: Maybe there's an opportunity to optimize and bail early?
     }
 
     unreachable!("unable to find a middle snake");
 }
 
+// Returns an empty snake at the end of the forward path found by iteration `d` which got the
+// furthest from (0, 0) without leaving the edit graph, as long as it lies strictly between its
+// corners so that splitting there makes progress
+fn furthest_forward_point(vf: &V, d: isize, n: usize, m: usize) -> Option<Snake> {
+    (-d..=d)
+        .step_by(2)
+        .map(|k| (vf[k], vf[k] as isize - k))
+        .filter(|&(x, y)| x <= n && y >= 0 && y as usize <= m)
+        .map(|(x, y)| (x, y as usize))
+        .filter(|&(x, y)| x + y > 0 && x + y < n + m)
+        .max_by_key(|&(x, y)| x + y)
+        .map(|(x, y)| Snake {
+            x_start: x,
+            y_start: y,
+            x_end: x,
+            y_end: y,
+        })
+}
+
+// Returns `true` if the deadline passed and part of the solution fell back to replacing a whole
+// range instead of diffing it
 fn conquer<'a, 'b, T: PartialEq>(
     mut old: Range<'a, [T]>,
     mut new: Range<'b, [T]>,
     vf: &mut V,
     vb: &mut V,
+    limits: Limits,
     solution: &mut Vec<DiffRange<'a, 'b, [T]>>,
-) {
+) -> bool {
     // Check for common prefix
     let common_prefix_len = old.common_prefix_len(new);
     if common_prefix_len > 0 {
@@ -211,6 +280,7 @@ fn conquer<'a, 'b, T: PartialEq>(
     old = old.slice(..old.len() - common_suffix_len);
     new = new.slice(..new.len() - common_suffix_len);
 
+    let mut timed_out = false;
     if old.is_empty() && new.is_empty() {
         // Do nothing
     } else if old.is_empty() {
@@ -219,23 +289,53 @@ fn conquer<'a, 'b, T: PartialEq>(
     } else if new.is_empty() {
         // Deletes
         solution.push(DiffRange::Delete(old));
-    } else {
+    } else if let Some((_shortest_edit_script_len, snake)) =
+        find_middle_snake(old, new, vf, vb, limits)
+    {
         // Divide & Conquer
-        let (_shortest_edit_script_len, snake) = find_middle_snake(old, new, vf, vb);
-
         let (old_a, old_b) = old.split_at(snake.x_start);
         let (new_a, new_b) = new.split_at(snake.y_start);
 
-        conquer(old_a, new_a, vf, vb, solution);
-        conquer(old_b, new_b, vf, vb, solution);
+        timed_out |= conquer(old_a, new_a, vf, vb, limits, solution);
+        timed_out |= conquer(old_b, new_b, vf, vb, limits, solution);
+    } else {
+        // Out of time, replace the whole range
+        solution.push(DiffRange::Delete(old));
+        solution.push(DiffRange::Insert(new));
+        timed_out = true;
     }
 
     if common_suffix_len > 0 {
         solution.push(common_suffix);
     }
+
+    timed_out
 }
 
 pub fn diff<'a, 'b, T: PartialEq>(old: &'a [T], new: &'b [T]) -> Vec<DiffRange<'a, 'b, [T]>> {
+    diff_with_limits(old, new, None, true).0
+}
+
+// Like `diff`, but once `deadline` passes the ranges which are left to diff are replaced
+// wholesale. Also returns whether that happened.
+//
+// Unless `minimal` is set, the search for each middle snake gives up after a number of
+// iterations growing with the square root of the size of the input, like git's xdiff does
+// without `--minimal`. That bounds the time spent on very different inputs, at the cost of a
+// diff which may be longer than necessary.
+pub fn diff_with_limits<'a, 'b, T: PartialEq>(
+    old: &'a [T],
+    new: &'b [T],
+    deadline: Option<Instant>,
+    minimal: bool,
+) -> (Vec<DiffRange<'a, 'b, [T]>>, bool) {
+    let max_cost = (!minimal).then(|| {
+        let size = old.len() + new.len();
+        let sqrt = (size as f64).sqrt() as usize;
+        cmp::max(sqrt, MAX_COST_MIN) as isize
+    });
+    let limits = Limits { deadline, max_cost };
+
     let old_recs = Range::new(old, ..);
     let new_recs = Range::new(new, ..);
 
@@ -248,14 +348,20 @@ pub fn diff<'a, 'b, T: PartialEq>(old: &'a [T], new: &'b [T]) -> Vec<DiffRange<'
     let mut vf = V::new(max_d);
     let mut vb = V::new(max_d);
 
-    conquer(old_recs, new_recs, &mut vf, &mut vb, &mut solution);
+    let timed_out = conquer(old_recs, new_recs, &mut vf, &mut vb, limits, &mut solution);
 
-    solution
+    (solution, timed_out)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::ops;
+
+    const UNLIMITED: Limits = Limits {
+        deadline: None,
+        max_cost: None,
+    };
 
     #[test]
     fn test_find_middle_snake() {
@@ -264,6 +370,81 @@ mod tests {
         let max_d = max_d(a.len(), b.len());
         let mut vf = V::new(max_d);
         let mut vb = V::new(max_d);
-        find_middle_snake(a, b, &mut vf, &mut vb);
+        find_middle_snake(a, b, &mut vf, &mut vb, UNLIMITED).unwrap();
+    }
+
+    // Fills `V` with values which would derail the search if they were ever read
+    fn poisoned(max_d: usize) -> V {
+        let mut v = V::new(max_d);
+        v.v.iter_mut().for_each(|x| *x = usize::MAX / 2);
+        v
+    }
+
+    type Span = Option<ops::Range<usize>>;
+
+    fn spans<T>(solution: &[DiffRange<[T]>]) -> Vec<(Span, Span)> {
+        solution
+            .iter()
+            .map(|diff| (diff.old_span(), diff.new_span()))
+            .collect()
+    }
+
+    #[test]
+    fn stale_v_entries_are_never_read() {
+        // Many small scattered changes force deep recursion through `conquer`
+        let old: Vec<u32> = (0..500).collect();
+        let new: Vec<u32> = old
+            .iter()
+            .filter(|&&i| i % 11 != 3)
+            .flat_map(|&i| match i % 7 {
+                0 => vec![i + 1000],
+                5 => vec![i, i + 2000],
+                _ => vec![i],
+            })
+            .collect();
+
+        let expected = diff(&old, &new);
+
+        let max_d = max_d(old.len(), new.len());
+        let mut vf = poisoned(max_d);
+        let mut vb = poisoned(max_d);
+        let mut solution = Vec::new();
+        conquer(
+            Range::new(&old[..], ..),
+            Range::new(&new[..], ..),
+            &mut vf,
+            &mut vb,
+            UNLIMITED,
+            &mut solution,
+        );
+
+        assert!(expected.len() > 100);
+        assert_eq!(spans(&solution), spans(&expected));
+    }
+
+    #[test]
+    fn solution_reconstructs_inputs() {
+        use crate::range::{reconstruct_new, reconstruct_old};
+
+        let cases: [(&[u8], &[u8]); 5] = [
+            (b"", b""),
+            (b"abc", b""),
+            (b"", b"abc"),
+            (b"ABCABBA", b"CBABAC"),
+            (b"the quick brown fox", b"a quick brown dog jumps"),
+        ];
+        for (old, new) in cases {
+            for minimal in [true, false] {
+                let (solution, _) = diff_with_limits(old, new, None, minimal);
+                assert_eq!(reconstruct_old(&solution).concat(), old);
+                assert_eq!(reconstruct_new(&solution).concat(), new);
+            }
+        }
+
+        // Each piece borrows from the input instead of copying it
+        let (old, new) = (&b"abcdef"[..], &b"abXdef"[..]);
+        let pieces = reconstruct_old(&diff(old, new));
+        assert_eq!(pieces, [&b"ab"[..], b"c", b"def"]);
+        assert_eq!(pieces[2].as_ptr(), old[3..].as_ptr());
     }
 }
@@ -1,10 +1,12 @@
 use super::*;
 use crate::{
-    apply::apply,
-    diff::{Diff, DiffRange},
-    patch::Patch,
-    range::Range,
+    apply::{apply, apply_bytes, apply_bytes_partial, apply_hunk, apply_partial, ApplyOptions},
+    diff::{Diff, DiffBuilder, DiffRange, EditOp, EditRange, Granularity},
+    patch::{DiffStats, HunkDivergence, HunkRange, LineKind, Patch, PatchFormatter},
+    range::{reconstruct_new, reconstruct_old, Range},
+    utils::LineEnding,
 };
+use std::borrow::Cow;
 
 // Helper macros are based off of the ones used in [dissimilar](https://docs.rs/dissimilar)
 macro_rules! diff_range_list {
@@ -518,6 +520,33 @@ fn no_newline_at_eof() {
     assert_patch!(old, new, expected);
 }
 
+#[test]
+fn only_trailing_newline_differs() {
+    let old = "a\nb";
+    let new = "a\nb\n";
+    let expected = "\
+--- original
++++ modified
+@@ -1,2 +1,2 @@
+ a
+-b
+\\ No newline at end of file
++b
+";
+    assert_patch!(old, new, expected);
+
+    let expected = "\
+--- original
++++ modified
+@@ -1,2 +1,2 @@
+ a
+-b
++b
+\\ No newline at end of file
+";
+    assert_patch!(new, old, expected);
+}
+
 #[test]
 fn myers_diffy_vs_git() {
     let original = "\
@@ -706,3 +735,3079 @@ Kupluh, Indeed
     let re_reverse = apply(&apply(original, &p).unwrap(), &reverse).unwrap();
     assert_eq!(re_reverse, original);
 }
+
+#[test]
+fn hunk_as_patch() {
+    let original = "\
+The Way that can be told of is not the eternal Way;
+The name that can be named is not the eternal name.
+The Nameless is the origin of Heaven and Earth;
+The Named is the mother of all things.
+Therefore let there always be non-being,
+  so we may see their subtlety,
+And let there always be being,
+  so we may see their outcome.
+The two are the same,
+But after they are produced,
+  they have different names.
+";
+    let modified = "\
+The Nameless is the origin of Heaven and Earth;
+The named is the mother of all things.
+Therefore let there always be non-being,
+  so we may see their subtlety,
+And let there always be being,
+  so we may see their outcome.
+The two are the same,
+But after they are produced,
+  they have different names.
+They both may be called deep and profound.
+";
+
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(1);
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+    assert!(patch.hunk_as_patch(2).is_none());
+
+    let first = patch.hunk_as_patch(0).unwrap();
+    assert_eq!(first.original(), patch.original());
+    assert_eq!(first.modified(), patch.modified());
+    assert_eq!(first.hunks(), &patch.hunks()[..1]);
+
+    let expected = "\
+--- original
++++ modified
+@@ -11 +9,2 @@
+   they have different names.
++They both may be called deep and profound.
+";
+    let last = patch.hunk_as_patch(1).unwrap();
+    assert_eq!(last.to_string(), expected);
+
+    let expected = "\
+The Nameless is the origin of Heaven and Earth;
+The named is the mother of all things.
+Therefore let there always be non-being,
+  so we may see their subtlety,
+And let there always be being,
+  so we may see their outcome.
+The two are the same,
+But after they are produced,
+  they have different names.
+";
+    assert_eq!(apply(original, &first).unwrap(), expected);
+
+    let expected = format!("{}They both may be called deep and profound.\n", original);
+    assert_eq!(apply(original, &last).unwrap(), expected);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn ignore_matching_lines() {
+    let original = "\
+// Generated at 2024-06-14
+fn one() {}
+fn two() {}
+fn three() {}
+fn four() {}
+fn five() {}
+fn six() {}
+fn seven() {}
+fn eight() {}
+// Generated by diffy
+fn nine() {}
+";
+    let modified = "\
+// Generated at 2024-06-15
+fn one() {}
+fn two() {}
+fn three() {}
+fn four() {}
+fn five() {}
+fn six() {}
+fn seven() {}
+fn eight() {}
+// Generated by diffy 0.4.0
+fn nine() -> usize { 9 }
+";
+
+    let expected = "\
+--- original
++++ modified
+@@ -7,5 +7,5 @@
+ fn six() {}
+ fn seven() {}
+ fn eight() {}
+-// Generated by diffy
+-fn nine() {}
++// Generated by diffy 0.4.0
++fn nine() -> usize { 9 }
+";
+
+    let mut opts = DiffOptions::default();
+    opts.set_ignore_matching_lines(regex::Regex::new("^// Generated").unwrap());
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.to_string(), expected);
+    let bpatch = opts.create_patch_bytes(original.as_bytes(), modified.as_bytes());
+    assert_eq!(bpatch.to_bytes(), expected.as_bytes());
+
+    // Without the option both hunks are present
+    assert_eq!(create_patch(original, modified).hunks().len(), 2);
+}
+
+#[test]
+fn write_to() {
+    let original = "one\ntwo\nthree\nfour\n";
+    let modified = "one\n2\nthree\nfour\nfive";
+
+    let patch = create_patch(original, modified);
+    let mut bytes = Vec::new();
+    patch.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, patch.to_string().as_bytes());
+
+    let bpatch = create_patch_bytes(original.as_bytes(), modified.as_bytes());
+    let mut bytes = Vec::new();
+    bpatch.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, bpatch.to_bytes());
+    assert_eq!(bytes, patch.to_string().as_bytes());
+}
+
+#[test]
+fn non_utf8_roundtrip() {
+    let original = b"one\n\xff\xfe two\nthree\n\xc3\x28\n";
+    let modified = b"one\n\xff\xff two\nthree\n\xc3\x28";
+    let expected = b"\
+--- original
++++ modified
+@@ -1,4 +1,4 @@
+ one
+-\xff\xfe two
++\xff\xff two
+ three
+-\xc3\x28
++\xc3\x28
+\\ No newline at end of file
+";
+
+    let patch = create_patch_bytes(original, modified);
+    let mut bytes = Vec::new();
+    patch.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, &expected[..]);
+
+    let parsed = Patch::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed, patch);
+    assert_eq!(parsed.to_bytes(), bytes);
+    assert_eq!(apply_bytes(original, &parsed).unwrap(), modified);
+}
+
+// Two back-to-back changes, without an unchanged line between them, are part of the same edit and
+// need to be rendered as a single hunk even when no context is requested.
+#[test]
+fn zero_context_adjacent_changes() {
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(0);
+
+    let old = "one\ntwo\nthree\nfour\n";
+    let new = "one\n2\n3\nfour\n";
+    let expected = "\
+--- original
++++ modified
+@@ -2,2 +2,2 @@
+-two
+-three
++2
++3
+";
+    assert_patch!(opts, old, new, expected);
+
+    let old = "one\ntwo\nthree\nfour\n";
+    let new = "one\n2\nfour\n";
+    let expected = "\
+--- original
++++ modified
+@@ -2,2 +2 @@
+-two
+-three
++2
+";
+    assert_patch!(opts, old, new, expected);
+
+    // A single unchanged line between changes splits them into separate hunks
+    let old = "one\ntwo\nthree\nfour\n";
+    let new = "1\ntwo\n3\nfour\n";
+    let expected = "\
+--- original
++++ modified
+@@ -1 +1 @@
+-one
++1
+@@ -3 +3 @@
+-three
++3
+";
+    assert_patch!(opts, old, new, expected);
+}
+
+#[test]
+fn test_lcs() {
+    let a = b"ABCABBA";
+    let b = b"CBABAC";
+    let subsequence = lcs(a, b);
+    assert_eq!(subsequence.len(), 4);
+    assert_eq!(lcs(b, a).len(), 4);
+
+    let a = ["one", "two", "three", "four"];
+    let b = ["zero", "two", "four", "five"];
+    assert_eq!(lcs(&a, &b), [&"two", &"four"]);
+
+    assert!(lcs(&a, &[]).is_empty());
+    assert_eq!(lcs(&a, &a).len(), a.len());
+}
+
+#[test]
+fn mutate_patch() {
+    let original = "\
+one
+two
+three
+four
+five
+six
+seven
+eight
+nine
+ten
+";
+    let modified = "\
+one
+2
+three
+four
+five
+six
+seven
+eight
+nine
+10
+";
+    let mut patch = create_patch(original, modified);
+    patch.validate().unwrap();
+
+    // Replace an inserted line and add a new one to the first hunk
+    let lines = patch.hunks_mut()[0].lines_mut();
+    let pos = lines
+        .iter()
+        .position(|line| *line == Line::Insert("2\n"))
+        .unwrap();
+    lines[pos] = Line::Insert("TWO\n");
+    lines.insert(pos + 1, Line::Insert("two and a half\n"));
+    patch.validate().unwrap_err();
+
+    patch.renumber();
+    patch.validate().unwrap();
+
+    let expected = "\
+--- original
++++ modified
+@@ -1,5 +1,6 @@
+ one
+-two
++TWO
++two and a half
+ three
+ four
+ five
+@@ -7,4 +8,4 @@
+ seven
+ eight
+ nine
+-ten
++10
+";
+    assert_eq!(patch.to_string(), expected);
+
+    let expected = "\
+one
+TWO
+two and a half
+three
+four
+five
+six
+seven
+eight
+nine
+10
+";
+    assert_eq!(apply(original, &patch).unwrap(), expected);
+
+    let range = patch.hunks_mut()[1].old_range_mut();
+    range.set_start(8);
+    range.set_len(3);
+    assert_eq!(patch.hunks()[1].old_range().range(), 8..11);
+    patch.validate().unwrap_err();
+}
+
+#[test]
+fn patch_into_iter() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\n";
+    let modified = "1\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n9\n";
+    let patch = create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+
+    let mut count = 0;
+    for hunk in &patch {
+        assert_eq!(hunk, &patch.hunks()[count]);
+        count += 1;
+    }
+    assert_eq!(count, patch.hunks().len());
+
+    let hunks: Vec<_> = patch.clone().into_iter().collect();
+    assert_eq!(hunks, patch.hunks());
+    assert_eq!(patch.into_iter().count(), hunks.len());
+}
+
+#[test]
+fn custom_line_prefixes() {
+    let original = "one\ntwo\nthree\n";
+    let modified = "one\n2\nthree\n";
+    let patch = create_patch(original, modified);
+
+    let f = PatchFormatter::new()
+        .with_context_prefix('=')
+        .with_delete_prefix('<')
+        .with_insert_prefix('>');
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,3 @@
+=one
+<two
+>2
+=three
+";
+    let custom = f.fmt_patch(&patch).to_string();
+    assert_eq!(custom, expected);
+
+    let mut bytes = Vec::new();
+    f.write_patch_into(&patch, &mut bytes).unwrap();
+    assert_eq!(bytes, expected.as_bytes());
+
+    // Translating the prefixes back results in the standard format
+    let standard: String = custom
+        .lines()
+        .map(|line| {
+            let line = match line.split_at(1) {
+                ("=", rest) => format!(" {}", rest),
+                ("<", rest) => format!("-{}", rest),
+                (">", rest) => format!("+{}", rest),
+                _ => line.to_owned(),
+            };
+            line + "\n"
+        })
+        .collect();
+    assert_eq!(Patch::from_str(&standard).unwrap(), patch);
+
+    // Defaults produce a standard unified diff
+    assert_eq!(
+        PatchFormatter::new().fmt_patch(&patch).to_string(),
+        patch.to_string()
+    );
+}
+
+#[test]
+fn word_diff() {
+    let original = "\
+The quick brown fox
+jumps over
+the lazy dog
+";
+    let modified = "\
+The slow brown fox
+jumps over
+the lazy  cat
+and runs away
+";
+    let patch = create_patch(original, modified);
+
+    let f = PatchFormatter::new().with_word_diff();
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,4 @@
+ The [-quick-]{+slow+} brown fox
+ jumps over
+ the lazy[- dog-]{+  cat+}
++and runs away
+";
+    assert_eq!(f.fmt_patch(&patch).to_string(), expected);
+
+    let mut bytes = Vec::new();
+    f.write_patch_into(&patch, &mut bytes).unwrap();
+    assert_eq!(bytes, expected.as_bytes());
+}
+
+// A leading BOM is treated as part of the first line so that a patch between files which only
+// differ by a BOM still applies cleanly in both directions.
+#[test]
+fn byte_order_mark() {
+    let old = "\u{feff}one\ntwo\nthree\n";
+    let new = "one\ntwo\nthree\n";
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,3 @@
+-\u{feff}one
++one
+ two
+ three
+";
+    assert_patch!(old, new, expected);
+
+    let patch = create_patch(old, new);
+    assert_eq!(apply(new, &patch.reverse()).unwrap(), old);
+}
+
+#[test]
+fn stats() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+    let modified = "1\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n9\n9.5\nten\n";
+    let patch = create_patch(original, modified);
+
+    let hunk_stats: Vec<_> = patch.hunks().iter().map(Hunk::stats).collect();
+    assert_eq!(hunk_stats.len(), 2);
+    assert_eq!(
+        (
+            hunk_stats[0].insertions(),
+            hunk_stats[0].deletions(),
+            hunk_stats[0].context()
+        ),
+        (1, 1, 3)
+    );
+    assert_eq!(
+        (
+            hunk_stats[1].insertions(),
+            hunk_stats[1].deletions(),
+            hunk_stats[1].context()
+        ),
+        (2, 1, 4)
+    );
+
+    let stats = patch.stats();
+    assert_eq!(stats, hunk_stats.into_iter().sum());
+    assert_eq!(
+        (stats.insertions(), stats.deletions(), stats.context()),
+        (3, 2, 7)
+    );
+
+    assert_eq!(
+        create_patch(original, original).stats(),
+        DiffStats::default()
+    );
+}
+
+#[test]
+fn count_changed_lines() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+    let modified = "1\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n9\n9.5\nten\n";
+    let opts = DiffOptions::new();
+
+    let stats = opts.create_patch(original, modified).stats();
+    let counts = opts.count_changed_lines(original, modified);
+    assert_eq!(counts, (stats.deletions(), stats.insertions()));
+    assert_eq!(counts, (2, 3));
+    assert_eq!(
+        opts.count_changed_lines_bytes(original.as_bytes(), modified.as_bytes()),
+        counts
+    );
+
+    assert_eq!(opts.count_changed_lines(original, original), (0, 0));
+    assert_eq!(opts.count_changed_lines("", original), (0, 10));
+}
+
+#[test]
+fn trim_context() {
+    let original = "\
+one
+two
+three
+four
+five
+six
+seven
+eight
+nine
+ten
+eleven
+twelve
+thirteen
+";
+    let modified = "\
+one
+two
+three
+four
+five
+six
+7
+eight
+9
+ten
+eleven
+twelve
+thirteen
+";
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(5);
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 1);
+
+    let trimmed = patch.hunks()[0].trim_context(1);
+    opts.set_context_len(1);
+    let expected = opts.create_patch(original, modified);
+    assert_eq!(trimmed, expected.hunks()[0]);
+    assert_eq!(trimmed.old_range().range(), 6..11);
+    assert_eq!(trimmed.new_range().range(), 6..11);
+
+    // Trimming to a larger context is a no-op
+    assert_eq!(patch.hunks()[0].trim_context(10), patch.hunks()[0]);
+
+    // Trimming all context from a pure insertion
+    let patch = create_patch("one\ntwo\nthree\n", "one\ntwo\n2.5\nthree\n");
+    let trimmed = patch.hunks()[0].trim_context(0);
+    assert_eq!(trimmed.lines(), [Line::Insert("2.5\n")]);
+    assert_eq!(trimmed.old_range(), HunkRange::new(2, 0));
+    assert_eq!(trimmed.new_range(), HunkRange::new(3, 1));
+}
+
+// When a hunk has an empty new range, its starting line number refers to the line preceding the
+// hunk. Apply needs to take this into account when searching for where to apply a hunk, otherwise
+// hunks without context can end up matching lines which precede where they should be applied.
+#[test]
+fn apply_zero_context_deletions() {
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(0);
+
+    let old = "\n\n\n\n\nb\n\nbb\n\n\n\n";
+    let new = "\nb\nbb\n";
+    let expected = "\
+--- original
++++ modified
+@@ -2,4 +1,0 @@
+-
+-
+-
+-
+@@ -7 +2,0 @@
+-
+@@ -9,3 +3,0 @@
+-
+-
+-
+";
+    assert_patch!(opts, old, new, expected);
+}
+
+// Texts made up of a small alphabet, to produce many repeated lines, with or without a trailing
+// newline and with a mix of LF and CRLF line endings
+fn text_strategy() -> impl proptest::strategy::Strategy<Value = String> {
+    proptest::string::string_regex("([abc]{0,2}\r?\n){0,12}[abc]{0,2}").unwrap()
+}
+
+proptest::proptest! {
+    #[test]
+    fn apply_create_patch_roundtrip(old in text_strategy(), new in text_strategy()) {
+        let mut opts = DiffOptions::default();
+        for context_len in 0..=5 {
+            opts.set_context_len(context_len);
+
+            let patch = opts.create_patch(&old, &new);
+            proptest::prop_assert_eq!(apply(&old, &patch).unwrap(), new.as_str());
+
+            let patch = opts.create_patch_bytes(old.as_bytes(), new.as_bytes());
+            proptest::prop_assert_eq!(
+                crate::apply_bytes(old.as_bytes(), &patch).unwrap(),
+                new.as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn solution_reconstructs_inputs(old in text_strategy(), new in text_strategy()) {
+        let mut opts = DiffOptions::default();
+        for minimal in [true, false] {
+            opts.set_minimal(minimal);
+            let solution = opts.diff_slice(old.as_bytes(), new.as_bytes());
+            proptest::prop_assert_eq!(reconstruct_old(&solution).concat(), old.as_bytes());
+            proptest::prop_assert_eq!(reconstruct_new(&solution).concat(), new.as_bytes());
+        }
+    }
+}
+
+#[test]
+fn expand_context() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\n";
+    let modified = "one\ntwo\n3\nfour\nfive\nsix\n";
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(1);
+    let patch = opts.create_patch(original, modified);
+    let hunk = &patch.hunks()[0];
+    assert_eq!(hunk.old_range().range(), 2..5);
+
+    // Expanding upwards stops at the start of the file
+    let expanded = hunk.expand_context(original, 5, 0);
+    assert_eq!(expanded.old_range().range(), 1..5);
+    assert_eq!(expanded.new_range().range(), 1..5);
+    assert_eq!(expanded.lines()[0], Line::Context("one\n"));
+
+    // Expanding downwards stops at the end of the file
+    let expanded = hunk.expand_context(original, 0, 5);
+    assert_eq!(expanded.old_range().range(), 2..7);
+    assert_eq!(expanded.lines().last(), Some(&Line::Context("six\n")));
+
+    // Expanding in both directions is the same as using a larger context
+    opts.set_context_len(2);
+    let expected = opts.create_patch(original, modified);
+    assert_eq!(hunk.expand_context(original, 1, 1), expected.hunks()[0]);
+
+    // Expanding a hunk without context
+    opts.set_context_len(0);
+    let patch = opts.create_patch(original, modified);
+    let expanded = patch.hunks()[0].expand_context(original, 1, 1);
+    assert_eq!(&expanded, hunk);
+
+    let patch = create_patch("", "one\n");
+    let expanded = patch.hunks()[0].expand_context("", 3, 3);
+    assert_eq!(expanded, patch.hunks()[0]);
+}
+
+#[test]
+fn expand_context_near_file_boundaries() {
+    // Earlier hunks removed lines, so the new file runs out of lines before the old one
+    let original = "x\ny\nz\na\nb\nc\nd\ne\n";
+    let modified = "a\nb\nc\nD\ne\n";
+    let patch = DiffOptions::new()
+        .set_context_len(0)
+        .create_patch(original, modified);
+    let hunk = &patch.hunks()[1];
+    let expanded = hunk.expand_context(original, 10, 10);
+    assert_eq!(expanded.old_range().range(), 4..9);
+    assert_eq!(expanded.new_range().range(), 1..6);
+    assert_eq!(
+        expanded.lines(),
+        [
+            Line::Context("a\n"),
+            Line::Context("b\n"),
+            Line::Context("c\n"),
+            Line::Delete("d\n"),
+            Line::Insert("D\n"),
+            Line::Context("e\n"),
+        ]
+    );
+    let mut expanded_patch = Patch::from_str("").unwrap();
+    expanded_patch.extend([patch.hunks()[0].clone(), expanded]);
+    assert_eq!(apply(original, &expanded_patch).unwrap(), modified);
+
+    // An original which is shorter than the hunk's range doesn't provide any lines
+    for short in ["", "x\n", "x\ny\nz\na\nb\n"] {
+        assert_eq!(&hunk.expand_context(short, 2, 2), hunk);
+    }
+
+    // A hunk at the very start or end of the file
+    let patch = create_patch("a\nb\n", "A\nb\nc\n");
+    for hunk in patch.hunks() {
+        assert_eq!(&hunk.expand_context("a\nb\n", 5, 5), hunk);
+    }
+}
+
+#[test]
+fn test_unicode_boundaries() {
+    fn assert_reconstructs(a: &str, b: &str, solution: &[Diff<str>]) {
+        let mut old = String::new();
+        let mut new = String::new();
+        for diff in solution {
+            match diff {
+                Diff::Equal(s) => {
+                    old.push_str(s);
+                    new.push_str(s);
+                }
+                Diff::Delete(s) => old.push_str(s),
+                Diff::Insert(s) => new.push_str(s),
+            }
+        }
+        assert_eq!(old, a);
+        assert_eq!(new, b);
+    }
+
+    // Multi-byte chars sitting right at the boundary of a change
+    let a = "caf\u{e9} au lait";
+    let b = "caf\u{e8} au lait";
+    let solution = diff(a, b);
+    assert_diff!(
+        [
+            Equal("caf"),
+            Delete("\u{e9}"),
+            Insert("\u{e8}"),
+            Equal(" au lait")
+        ],
+        solution,
+    );
+    assert_reconstructs(a, b, &solution);
+
+    // Pure emoji, many of which share leading bytes
+    let a = "\u{1f600}\u{1f601}\u{1f602}\u{1f603}";
+    let b = "\u{1f600}\u{1f604}\u{1f602}\u{1f605}";
+    let solution = diff(a, b);
+    assert_diff!(
+        [
+            Equal("\u{1f600}"),
+            Delete("\u{1f601}"),
+            Insert("\u{1f604}"),
+            Equal("\u{1f602}"),
+            Delete("\u{1f603}"),
+            Insert("\u{1f605}"),
+        ],
+        solution,
+    );
+    assert_reconstructs(a, b, &solution);
+
+    // Right-to-left text mixed with left-to-right text
+    let a = "hello \u{5e9}\u{5dc}\u{5d5}\u{5dd} world";
+    let b = "hello \u{5e9}\u{5dc}\u{5d5}\u{5dd}\u{5d9} world";
+    let solution = diff(a, b);
+    assert_diff!(
+        [
+            Equal("hello \u{5e9}\u{5dc}\u{5d5}\u{5dd}"),
+            Insert("\u{5d9}"),
+            Equal(" world"),
+        ],
+        solution,
+    );
+    assert_reconstructs(a, b, &solution);
+
+    let a = "\u{645}\u{631}\u{62d}\u{628}\u{627}";
+    let b = "\u{645}\u{631}\u{62d}\u{628}\u{627} \u{1f44b}";
+    let solution = diff(a, b);
+    assert_reconstructs(a, b, &solution);
+    let solution = diff(b, a);
+    assert_reconstructs(b, a, &solution);
+}
+
+#[test]
+fn sort_hunks() {
+    let original = "\
+one
+two
+three
+four
+five
+six
+seven
+eight
+nine
+ten
+eleven
+twelve
+thirteen
+fourteen
+fifteen
+sixteen
+";
+    let modified = "\
+1
+two
+three
+four
+five
+six
+seven
+8
+nine
+ten
+eleven
+twelve
+thirteen
+fourteen
+fifteen
+16
+";
+    let mut patch = create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 3);
+    let expected = patch.clone();
+
+    patch.hunks_mut().swap(0, 2);
+    patch.hunks_mut().swap(1, 2);
+    patch.validate().unwrap_err();
+
+    patch.sort_hunks();
+    patch.validate().unwrap();
+    assert_eq!(patch, expected);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+
+    // Overlapping hunks are invalid
+    let mut patch = expected.clone();
+    let hunk = patch.hunks()[0].clone();
+    patch.hunks_mut().insert(1, hunk);
+    patch.validate().unwrap_err();
+}
+
+#[test]
+fn truncate_hunks() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+    let modified = "1\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n9\nten\n";
+    let patch = create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+
+    let mut truncated = patch.clone();
+    assert_eq!(truncated.truncate_hunks(5), 0);
+    assert_eq!(truncated, patch);
+
+    assert_eq!(truncated.truncate_hunks(1), 1);
+    assert_eq!(truncated.hunks(), &patch.hunks()[..1]);
+    assert_eq!(
+        apply(original, &truncated).unwrap(),
+        "1\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n"
+    );
+
+    assert_eq!(truncated.truncate_hunks(0), 1);
+    assert!(truncated.hunks().is_empty());
+}
+
+#[test]
+fn hunk_range_display() {
+    fn header(original: &str, modified: &str) -> String {
+        let patch = DiffOptions::new()
+            .set_context_len(0)
+            .create_patch(original, modified);
+        let hunk = &patch.hunks()[0];
+        format!("-{} +{}", hunk.old_range(), hunk.new_range())
+    }
+
+    // Empty old side
+    assert_eq!(header("", "one\ntwo\n"), "-0,0 +1,2");
+    assert_eq!(header("one\ntwo\n", "one\ntwo\nthree\n"), "-2,0 +3");
+    // Empty new side
+    assert_eq!(header("one\ntwo\n", ""), "-1,2 +0,0");
+    assert_eq!(header("one\ntwo\nthree\n", "one\ntwo\n"), "-3 +2,0");
+    // Both sides non-empty
+    assert_eq!(header("one\n", "two\n"), "-1 +1");
+    assert_eq!(header("one\ntwo\n", "1\n2\n3\n"), "-1,2 +1,3");
+
+    assert_eq!(HunkRange::new(0, 0).to_string(), "0,0");
+    assert_eq!(HunkRange::new(11, 0).to_string(), "11,0");
+    assert_eq!(HunkRange::new(11, 1).to_string(), "11");
+    assert_eq!(HunkRange::new(11, 3).to_string(), "11,3");
+}
+
+#[test]
+fn normal_format() {
+    let original = "one\ntwo\nthree\nfour\n";
+    let modified = "zero\none\n2\nthree\n";
+    let normal = "\
+0a1
+> zero
+2c3
+< two
+---
+> 2
+4d4
+< four
+";
+
+    let patch = Patch::from_normal_str(normal).unwrap();
+    assert_eq!(patch.hunks().len(), 3);
+    assert_eq!(patch.to_normal_string(), normal);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+    assert_eq!(create_patch(original, modified).to_normal_string(), normal);
+
+    let original = "one\ntwo\nthree";
+    let modified = "one\n2\n3\n4";
+    let normal = "\
+2,3c2,4
+< two
+< three
+\\ No newline at end of file
+---
+> 2
+> 3
+> 4
+\\ No newline at end of file
+";
+    let patch = Patch::from_normal_str(normal).unwrap();
+    assert_eq!(patch.to_normal_string(), normal);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+    assert_eq!(create_patch(original, modified).to_normal_string(), normal);
+
+    Patch::from_normal_str("2x3\n").unwrap_err();
+    Patch::from_normal_str("2c2\n< two\n> 2\n").unwrap_err();
+    Patch::from_normal_str("2,3d1\n< two\n").unwrap_err();
+    Patch::from_normal_str("4d4\n< four\n0a1\n> zero\n").unwrap_err();
+}
+
+#[test]
+fn lines_only() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+    let modified = "1\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n9\n9.5\nten";
+    let patch = create_patch(original, modified);
+
+    let f = PatchFormatter::new().with_lines_only(LineKind::Insert);
+    assert_eq!(f.fmt_patch(&patch).to_string(), "1\n9\n9.5\nten");
+
+    let f = PatchFormatter::new()
+        .with_color()
+        .with_lines_only(LineKind::Delete);
+    assert_eq!(f.fmt_patch(&patch).to_string(), "one\nnine\nten\n");
+
+    let bpatch = create_patch_bytes(original.as_bytes(), modified.as_bytes());
+    let mut bytes = Vec::new();
+    f.write_patch_into(&bpatch, &mut bytes).unwrap();
+    assert_eq!(bytes, b"one\nnine\nten\n");
+}
+
+// Pins the shape of diffs for inputs with multiple optimal alignments, see the "Output" section
+// of the `DiffOptions` docs.
+#[test]
+fn tie_breaking() {
+    let original = "A\nB\nC\nA\nB\nB\nA\n";
+    let modified = "C\nB\nA\nB\nA\nC\n";
+    let expected = "\
+--- original
++++ modified
+@@ -1,7 +1,6 @@
+-A
+-B
+ C
+-A
+ B
++A
+ B
+ A
++C
+";
+    assert_patch!(original, modified, expected);
+
+    let expected = "\
+--- original
++++ modified
+@@ -1,6 +1,7 @@
+-C
++A
+ B
++C
+ A
+ B
++B
+ A
+-C
+";
+    assert_patch!(modified, original, expected);
+
+    // Changes to a run of identical lines are placed at its end
+    let original = "a\na\na\n";
+    let modified = "a\na\n";
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,2 @@
+ a
+ a
+-a
+";
+    assert_patch!(original, modified, expected);
+}
+
+#[test]
+fn stat_summary() {
+    let original: String = (0..100).map(|i| format!("old {}\n", i)).collect();
+    let mut modified: String = (0..50).map(|i| format!("old {}\n", i)).collect();
+    modified.extend((0..100).map(|i| format!("new {}\n", i)));
+    let patch = create_patch(&original, &modified);
+    let stats = patch.stats();
+    assert_eq!((stats.insertions(), stats.deletions()), (100, 50));
+
+    let summary = patch.stat_summary(60);
+    let lines: Vec<_> = summary.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].len(), 60);
+    assert_eq!(
+        lines[0],
+        format!(" modified | 150 {}{}", "+".repeat(29), "-".repeat(15))
+    );
+    assert_eq!(
+        lines[1],
+        " 1 file changed, 100 insertions(+), 50 deletions(-)"
+    );
+
+    // No scaling when there is enough room
+    let summary = patch.stat_summary(200);
+    assert_eq!(
+        summary.lines().next().unwrap(),
+        format!(" modified | 150 {}{}", "+".repeat(100), "-".repeat(50))
+    );
+
+    let patch = create_patch("one\n", "");
+    assert_eq!(
+        patch.stat_summary(80),
+        " modified | 1 -\n 1 file changed, 1 deletion(-)\n"
+    );
+
+    // The graph as a whole is scaled, so rounding both sides up doesn't overflow the width
+    let patch = create_patch("a\nb\n", "c\nd\n");
+    let summary = patch.stat_summary(17);
+    assert_eq!(summary.lines().next(), Some(" modified | 4 +--"));
+    let patch = create_patch("a\nb\nc\n", "d\n");
+    assert_eq!(
+        patch.stat_summary(16).lines().next(),
+        Some(" modified | 4 +-")
+    );
+
+    // Like git, the `a/` and `b/` prefixes of the filenames are left out
+    let patch =
+        Patch::from_str("--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n").unwrap();
+    assert_eq!(
+        patch.stat_summary(80),
+        " src/lib.rs | 2 +-\n 1 file changed, 1 insertion(+), 1 deletion(-)\n"
+    );
+}
+
+#[test]
+fn patch_set_stat_summary() {
+    let big: String = (0..100).map(|i| format!("{}\n", i)).collect();
+    let small: String = (0..10).map(|i| format!("{}\n", i)).collect();
+    let patches = DiffBuilder::new()
+        .add_file("big", "", &big, 3)
+        .add_file("small", &small, "", 3)
+        .build();
+
+    // Both graphs are scaled by the same factor
+    let summary = patches.stat_summary(40);
+    let lines: Vec<_> = summary.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            format!(" big   | 100 {}", "+".repeat(27)),
+            format!(" small |  10 {}", "-".repeat(3)),
+            " 2 files changed, 100 insertions(+), 10 deletions(-)".to_owned(),
+        ]
+    );
+    assert_eq!(lines[0].len(), 40);
+
+    assert_eq!(
+        patches.stat_summary(200).lines().nth(1),
+        Some(format!(" small |  10 {}", "-".repeat(10)).as_str())
+    );
+    assert_eq!(DiffBuilder::new().build().stat_summary(80), "");
+}
+
+#[test]
+fn normalizer() {
+    let original = "\
+Alpha
+beta
+gamma
+delta
+";
+    let modified = "\
+alpha
+  Beta\t
+gamma
+DELTA
+";
+    let mut opts = DiffOptions::new();
+    opts.set_normalizer(|line| Cow::Owned(line.trim().to_lowercase()));
+    assert!(opts.create_patch(original, modified).hunks().is_empty());
+    assert!(opts
+        .create_patch_bytes(original.as_bytes(), modified.as_bytes())
+        .hunks()
+        .is_empty());
+    assert_eq!(opts.count_changed_lines(original, modified), (0, 0));
+
+    let modified = "\
+alpha
+  Beta\t
+epsilon
+DELTA
+";
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,4 @@
+ alpha
+   Beta\t
+-gamma
++epsilon
+ DELTA
+";
+    assert_eq!(opts.create_patch(original, modified).to_string(), expected);
+
+    // Normalizers which borrow from the line are supported as well
+    opts.set_normalizer(|line| Cow::Borrowed(line.trim_end()));
+    assert!(opts
+        .create_patch("one\ntwo\n", "one  \ntwo\t\n")
+        .hunks()
+        .is_empty());
+}
+
+#[test]
+fn context_len_bounds() {
+    let original = "\
+one
+two
+three
+four
+five
+six
+seven
+eight
+nine
+ten
+eleven
+twelve
+thirteen
+fourteen
+fifteen
+sixteen
+";
+    // Changes on lines 1, 11 and 16, with 9 and 4 unchanged lines between them
+    let modified = original
+        .replace("one\n", "1\n")
+        .replace("eleven\n", "11\n")
+        .replace("sixteen\n", "16\n");
+    let opts = DiffOptions::new();
+
+    assert_eq!(opts.context_len_to_merge_all(original, &modified), 5);
+    assert_eq!(opts.max_distinct_context_len(original, &modified), Some(2));
+
+    let hunks = |context_len| {
+        DiffOptions::new()
+            .set_context_len(context_len)
+            .create_patch(original, &modified)
+            .hunks()
+            .len()
+    };
+    assert_eq!(hunks(2), 3);
+    assert_eq!(hunks(3), 2);
+    assert_eq!(hunks(4), 2);
+    assert_eq!(hunks(5), 1);
+
+    assert_eq!(opts.context_len_to_merge_all(original, original), 0);
+    assert_eq!(opts.max_distinct_context_len(original, original), None);
+    assert_eq!(opts.max_distinct_context_len(original, "one\n"), None);
+}
+
+#[test]
+fn ignore_space_change() {
+    let original = "\
+fn main() {
+\tlet a = 1;\t
+\tif a == 1 {
+\t\tprintln!(\"one\");
+\t}
+}
+";
+    let modified = "\
+fn main() {
+    let a  =  1;
+    if a == 1 {\r
+        println!(\"one\");
+    }
+}
+";
+    let mut opts = DiffOptions::new();
+    assert_eq!(opts.count_changed_lines(original, modified), (4, 4));
+
+    opts.set_ignore_space_change(true);
+    assert!(opts.create_patch(original, modified).hunks().is_empty());
+    assert!(opts
+        .create_patch_bytes(original.as_bytes(), modified.as_bytes())
+        .hunks()
+        .is_empty());
+
+    // Adding or removing whitespace entirely is still a change
+    assert_eq!(opts.count_changed_lines("a b\n", "ab\n"), (1, 1));
+    assert_eq!(opts.count_changed_lines("a\n", " a\n"), (1, 1));
+    // And so is a missing newline at the end of the file
+    assert_eq!(opts.count_changed_lines("a\n", "a"), (1, 1));
+    assert_eq!(opts.count_changed_lines("a \n", "a\n"), (0, 0));
+}
+
+#[test]
+fn apply_partial_collects_rejects() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n";
+    let modified = "1\ntwo\n3\n4\n5\n6\n7\neight\n9\n10\n11\n12\n13\nfourteen\n15\n";
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 3);
+
+    // The middle hunk no longer matches the base
+    let base = original.replace("7\n8\n9", "7\nacht\n9");
+    let (image, rejected) = apply_partial(&base, &patch);
+    assert_eq!(
+        image,
+        "1\ntwo\n3\n4\n5\n6\n7\nacht\n9\n10\n11\n12\n13\nfourteen\n15\n"
+    );
+    assert_eq!(rejected, &patch.hunks()[1..2]);
+    assert!(apply(&base, &patch).is_err());
+
+    // Everything applies cleanly to the original
+    let (image, rejected) = apply_partial(original, &patch);
+    assert_eq!(image, modified);
+    assert!(rejected.is_empty());
+
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch_bytes(original.as_bytes(), modified.as_bytes());
+    let (image, rejected) = apply_bytes_partial(base.as_bytes(), &patch);
+    assert_eq!(
+        image,
+        b"1\ntwo\n3\n4\n5\n6\n7\nacht\n9\n10\n11\n12\n13\nfourteen\n15\n"
+    );
+    assert_eq!(rejected, &patch.hunks()[1..2]);
+}
+
+#[test]
+fn apply_preserves_crlf_line_endings() {
+    let original = "a\nb\nc\nd\n";
+    let modified = "a\nB\nc\nd\ne\n";
+    let patch = create_patch(original, modified);
+
+    let base = "a\r\nb\r\nc\r\nd\r\n";
+    let expected = "a\r\nB\r\nc\r\nd\r\ne\r\n";
+    assert_eq!(apply(base, &patch).unwrap(), expected);
+    assert_eq!(apply_partial(base, &patch), (expected.to_owned(), vec![]));
+
+    let patch = create_patch_bytes(original.as_bytes(), modified.as_bytes());
+    assert_eq!(
+        apply_bytes(base.as_bytes(), &patch).unwrap(),
+        expected.as_bytes()
+    );
+
+    // A patch made from CRLF files applies as before
+    let patch = create_patch(base, expected);
+    assert_eq!(apply(base, &patch).unwrap(), expected);
+
+    // LF bases are matched exactly
+    let patch = create_patch(base, expected);
+    assert!(apply(original, &patch).is_err());
+
+    // A patch converting the line endings of a CRLF file isn't undone
+    let patch = create_patch(base, original);
+    assert_eq!(apply(base, &patch).unwrap(), original);
+    let patch = create_patch(base, "a\r\nb\nc\r\nd\r\n");
+    assert_eq!(apply(base, &patch).unwrap(), "a\r\nb\nc\r\nd\r\n");
+}
+
+#[test]
+fn lcs_by_key_compares_keys_only() {
+    #[derive(Debug, PartialEq)]
+    struct Record {
+        id: u32,
+        name: &'static str,
+    }
+
+    let record = |id, name| Record { id, name };
+    let old = [
+        record(1, "a"),
+        record(2, "b"),
+        record(3, "c"),
+        record(4, "d"),
+    ];
+    let new = [
+        record(2, "B"),
+        record(5, "e"),
+        record(4, "D"),
+        record(1, "A"),
+    ];
+
+    let common = lcs_by_key(&old, &new, |r| r.id);
+    assert_eq!(common, [&old[1], &old[3]]);
+
+    assert!(lcs_by_key(&old, &new, |r| r.name).is_empty());
+    assert!(lcs_by_key(&old, &[], |r| r.id).is_empty());
+}
+
+#[test]
+fn patch_summary() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let modified = "one\n2\n3\n4\n5\n6\n7\n8\nnine\nten\n11\n";
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    let patch = Patch::new(
+        Some("a/src/foo.rs"),
+        Some("b/src/foo.rs"),
+        patch.hunks().to_vec(),
+    );
+    assert_eq!(patch.summary(), "2 hunks, +4 -3 across b/src/foo.rs");
+
+    let patch = Patch::from_str("--- /dev/null\n+++ /dev/null\n").unwrap();
+    assert_eq!(patch.summary(), "0 hunks, +0 -0 across /dev/null");
+
+    let deleted = Patch::from_str("--- a/gone\n+++ /dev/null\n@@ -1 +0,0 @@\n-x\n").unwrap();
+    assert_eq!(deleted.summary(), "1 hunk, +0 -1 across a/gone");
+
+    let patch = Patch::from_normal_str("1d0\n< x\n").unwrap();
+    assert_eq!(patch.summary(), "1 hunk, +0 -1");
+}
+
+#[test]
+fn whitespace_only_patch() {
+    let original = "\
+fn main() {
+\tlet x = 1;
+\tif x == 1 {
+\t\tprintln!(\"{}\", x);
+\t}
+}
+";
+    let reindented = "\
+fn main() {
+    let x = 1;
+    if x == 1 {
+        println!(\"{}\", x);
+    }
+
+}
+";
+    let changed = "\
+fn main() {
+    let x = 2;
+    if x == 1 {
+        println!(\"{}\", x);
+    }
+}
+";
+    assert!(create_patch(original, reindented).is_whitespace_only());
+    assert!(create_patch_bytes(original.as_bytes(), reindented.as_bytes()).is_whitespace_only());
+    assert!(!create_patch(original, changed).is_whitespace_only());
+    assert!(create_patch(original, original).is_whitespace_only());
+
+    // Splitting a line is still only a whitespace change
+    assert!(create_patch("a b\n", "a\nb\n").is_whitespace_only());
+    assert!(!create_patch("a\n", "").is_whitespace_only());
+}
+
+#[test]
+fn patch_from_edit_script() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n";
+    let modified = "0\n1\n2\nthree\n4\n5\n6\n8\nnine\n";
+    let script = [
+        EditRange::new(0..0, 0..1),
+        EditRange::new(2..3, 3..4),
+        EditRange::new(6..7, 7..7),
+        EditRange::new(8..8, 8..9),
+    ];
+    let expected = "\
+--- original
++++ modified
+@@ -1 +1,2 @@
++0
+ 1
+@@ -2,3 +3,3 @@
+ 2
+-3
++three
+ 4
+@@ -6,3 +7,3 @@
+ 6
+-7
+ 8
++nine
+";
+
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch_from_edit_script(original, modified, &script);
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+
+    let patch = DiffOptions::new().create_patch_from_edit_script(original, original, &[]);
+    assert!(patch.hunks().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "isn't separated from the previous edit")]
+fn patch_from_adjacent_edits() {
+    let script = [EditRange::new(0..1, 0..1), EditRange::new(1..2, 1..2)];
+    DiffOptions::new().create_patch_from_edit_script("a\nb\n", "A\nB\n", &script);
+}
+
+#[test]
+#[should_panic(expected = "different number of unchanged lines")]
+fn patch_from_mismatched_edit_script() {
+    let script = [EditRange::new(0..1, 0..1)];
+    DiffOptions::new().create_patch_from_edit_script("a\nb\n", "A\n", &script);
+}
+
+#[test]
+fn numbered_lines() {
+    let s = "\
+--- a/file
++++ b/file
+@@ -2,4 +2,3 @@
+ two
+-three
+-four
++4
+ five
+@@ -10,0 +10,2 @@
++ten and a half
++ten and three quarters
+@@ -20 +21,0 @@
+-twenty
+";
+    let patch = Patch::from_str(s).unwrap();
+    fn numbers<'a>(hunk: &Hunk<'a, str>) -> Vec<(Option<usize>, Option<usize>, &'a str)> {
+        hunk.numbered_lines()
+            .map(|(old, new, line)| (old, new, line.content()))
+            .collect()
+    }
+
+    assert_eq!(
+        numbers(&patch.hunks()[0]),
+        [
+            (Some(2), Some(2), "two\n"),
+            (Some(3), None, "three\n"),
+            (Some(4), None, "four\n"),
+            (None, Some(3), "4\n"),
+            (Some(5), Some(4), "five\n"),
+        ]
+    );
+    assert_eq!(
+        numbers(&patch.hunks()[1]),
+        [
+            (None, Some(10), "ten and a half\n"),
+            (None, Some(11), "ten and three quarters\n"),
+        ]
+    );
+    assert_eq!(numbers(&patch.hunks()[2]), [(Some(20), None, "twenty\n")]);
+}
+
+#[test]
+fn ignore_comment_lines() {
+    let original = "\
+// Adds one
+fn inc(x: u32) -> u32 {
+    x + 1 // cheap
+}
+";
+    let reworded = "\
+// Returns x plus one
+fn inc(x: u32) -> u32 {
+    x + 1 // cheap
+}
+";
+    let changed = "\
+// Returns x plus one
+fn inc(x: u32) -> u32 {
+    x + 2 // cheap
+}
+";
+
+    let mut opts = DiffOptions::new();
+    assert_eq!(opts.create_patch(original, reworded).hunks().len(), 1);
+
+    opts.set_ignore_comment_lines(vec!["//".to_owned()]);
+    assert!(opts.create_patch(original, reworded).hunks().is_empty());
+    assert!(opts
+        .create_patch_bytes(original.as_bytes(), reworded.as_bytes())
+        .hunks()
+        .is_empty());
+
+    // Comment lines are kept as context around real changes
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,4 @@
+ // Returns x plus one
+ fn inc(x: u32) -> u32 {
+-    x + 1 // cheap
++    x + 2 // cheap
+ }
+";
+    assert_eq!(opts.create_patch(original, changed).to_string(), expected);
+
+    // Adding a comment line is still a change
+    let added = format!("// Increments\n{}", original);
+    assert_eq!(opts.count_changed_lines(original, &added), (0, 1));
+}
+
+#[test]
+fn diff_against() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let modified = "1\ntwo\n3\n4\n5\n6\n7\n8\nnine\n10\n";
+    let patch = create_patch(original, modified);
+    assert!(patch.diff_against(&patch).is_empty());
+    assert!(patch.diff_against(&patch.clone()).is_empty());
+
+    // The same changes with less context don't diverge
+    let tight = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(tight.hunks().len(), 2);
+    assert!(patch.diff_against(&tight).is_empty());
+
+    // Only the hunk changing line 9 differs
+    let other = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch(original, "1\ntwo\n3\n4\n5\n6\n7\n8\nnueve\n10\n");
+    assert_eq!(
+        tight.diff_against(&other),
+        [
+            HunkDivergence::OnlyInSelf(&tight.hunks()[1]),
+            HunkDivergence::OnlyInOther(&other.hunks()[1]),
+        ]
+    );
+}
+
+#[test]
+fn repair_overlaps() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n";
+    let first = "1\n2\nthree\n4\n5\n6\n7\n8\n";
+    let both = "1\n2\nthree\n4\n5\nsix\n7\n8\n";
+    let mut opts = DiffOptions::new();
+    opts.set_context_len(2);
+
+    // Concatenating the hunks of two patches leaves their context overlapping
+    let mut hunks = opts.create_patch(first, both).hunks().to_vec();
+    hunks.extend_from_slice(opts.create_patch(original, first).hunks());
+    let mut patch = Patch::new(Some("original"), Some("modified"), hunks);
+    assert!(patch.validate().is_err());
+
+    patch.repair_overlaps().unwrap();
+    patch.validate().unwrap();
+    assert_eq!(patch, opts.create_patch(original, both));
+    assert_eq!(apply(original, &patch).unwrap(), both);
+
+    // Line 3 is changed by one hunk but is context in the other
+    let mut hunks = opts.create_patch(original, first).hunks().to_vec();
+    hunks.extend_from_slice(
+        opts.create_patch(first, "1\n2\nthree\n4\nfive\n6\n7\n8\n")
+            .hunks(),
+    );
+    let mut patch = Patch::new(Some("original"), Some("modified"), hunks);
+    let unrepaired = patch.clone();
+    assert_eq!(
+        patch.repair_overlaps().unwrap_err().to_string(),
+        "conflicting overlap at hunk #2"
+    );
+    assert_eq!(patch, unrepaired);
+}
+
+#[test]
+fn identical_texts_skip_the_diff() {
+    let text = "line\n".repeat(10_000);
+    let copy = text.clone();
+
+    let patch = create_patch(&text, &copy);
+    assert!(patch.hunks().is_empty());
+    assert_eq!(patch.to_string(), "--- original\n+++ modified\n");
+
+    let patch = create_patch_bytes(text.as_bytes(), copy.as_bytes());
+    assert!(patch.hunks().is_empty());
+    assert_eq!(patch.to_bytes(), b"--- original\n+++ modified\n");
+
+    assert_eq!(DiffOptions::new().count_changed_lines(&text, &copy), (0, 0));
+}
+
+#[test]
+fn create_patch_with_hints() {
+    let original = "[entry]\nid = 1\n[entry]\nid = 2\n";
+    let modified = "[entry]\nid = 2\n";
+    let opts = DiffOptions::new();
+
+    // Without a hint the remaining header is matched with the first entry's
+    let unhinted = opts.create_patch(original, modified);
+    assert_eq!(
+        unhinted.to_string(),
+        "\
+--- original
++++ modified
+@@ -1,4 +1,2 @@
+ [entry]
+-id = 1
+-[entry]
+ id = 2
+"
+    );
+    assert_eq!(
+        opts.create_patch_with_hints(original, modified, &[])
+            .unwrap(),
+        unhinted
+    );
+
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,2 @@
+-[entry]
+-id = 1
+ [entry]
+ id = 2
+";
+    let patch = opts
+        .create_patch_with_hints(original, modified, &[(2, 0)])
+        .unwrap();
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+    let patch = opts
+        .create_patch_with_hints(original, modified, &[(2, 0), (3, 1)])
+        .unwrap();
+    assert_eq!(patch.to_string(), expected);
+
+    let invalid_hint = |hints: &[(usize, usize)]| {
+        opts.create_patch_with_hints(original, modified, hints)
+            .unwrap_err()
+            .index()
+    };
+    // Out of order
+    assert_eq!(invalid_hint(&[(3, 1), (2, 0)]), 1);
+    assert_eq!(invalid_hint(&[(2, 0), (2, 1)]), 1);
+    // Out of bounds
+    assert_eq!(invalid_hint(&[(4, 1)]), 0);
+    // Differing lines
+    assert_eq!(invalid_hint(&[(1, 1)]), 0);
+
+    // The texts between the hints are diffed with one deadline for the whole patch
+    let original: String = (0..1000).map(|i| format!("{}\n", i)).collect();
+    let modified: String = (0..1000)
+        .map(|i| match i % 3 {
+            0 => format!("{}\n", i * 7),
+            _ => format!("{}\n", i),
+        })
+        .collect();
+    let hints: Vec<_> = (1..1000).step_by(99).map(|i| (i, i)).collect();
+    let full = opts
+        .create_patch_with_hints(&original, &modified, &hints)
+        .unwrap();
+    let patch = DiffOptions::new()
+        .set_timeout(std::time::Duration::from_secs(60))
+        .create_patch_with_hints(&original, &modified, &hints)
+        .unwrap();
+    assert_eq!(patch, full);
+    let coarse = DiffOptions::new()
+        .set_timeout(std::time::Duration::ZERO)
+        .create_patch_with_hints(&original, &modified, &hints)
+        .unwrap();
+    assert!(coarse.stats().deletions() > full.stats().deletions());
+    assert_eq!(apply(&original, &coarse).unwrap(), modified);
+}
+
+#[test]
+fn diff_lines() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let modified = "0\n1\n2\n3\n4\n5\nsix\n7\n8\n10\n11\n";
+    let opts = DiffOptions::new();
+    let lines = opts.diff_lines(original, modified);
+
+    let stats = create_patch(original, modified).stats();
+    assert_eq!(lines.len(), original.lines().count() + stats.insertions());
+    let kinds: String = lines
+        .iter()
+        .map(|line| match line.kind() {
+            LineKind::Context => ' ',
+            LineKind::Delete => '-',
+            LineKind::Insert => '+',
+        })
+        .collect();
+    assert_eq!(kinds, "+     -+  - +");
+
+    // Reassembling either side gives back the original texts
+    let old: String = lines
+        .iter()
+        .filter(|line| line.kind() != LineKind::Insert)
+        .map(|line| line.content())
+        .collect();
+    let new: String = lines
+        .iter()
+        .filter(|line| line.kind() != LineKind::Delete)
+        .map(|line| line.content())
+        .collect();
+    assert_eq!((old.as_str(), new.as_str()), (original, modified));
+
+    assert_eq!(
+        opts.diff_lines_bytes(original.as_bytes(), modified.as_bytes())
+            .len(),
+        lines.len()
+    );
+    assert!(opts.diff_lines("", "").is_empty());
+}
+
+#[test]
+fn line_ending_settings() {
+    let original = "a\r\nb\r\nc\r\n";
+    let modified = "a\nB\nc\n";
+
+    // By default a change in line endings is a change
+    let mut opts = DiffOptions::new();
+    assert_eq!(opts.count_changed_lines(original, modified), (3, 3));
+
+    opts.set_line_ending(LineEnding::Auto);
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(opts.count_changed_lines(original, modified), (1, 1));
+    // The original is CRLF so the LF lines are written with CRLF
+    assert_eq!(apply(original, &patch).unwrap(), "a\r\nB\r\nc\r\n");
+
+    let mut apply_opts = ApplyOptions::new();
+    apply_opts.set_line_ending(LineEnding::Lf);
+    assert_eq!(apply_opts.apply(original, &patch).unwrap(), "a\nB\nc\n");
+    apply_opts.set_line_ending(LineEnding::CrLf);
+    assert_eq!(
+        apply_opts
+            .apply_bytes(original.as_bytes(), &create_patch_bytes(b"b\n", b"B\n"))
+            .unwrap(),
+        b"a\r\nB\r\nc\r\n"
+    );
+
+    // Auto doesn't ignore line endings when the original is LF
+    assert_eq!(opts.count_changed_lines(modified, original), (3, 3));
+    opts.set_line_ending(LineEnding::Lf);
+    assert_eq!(opts.count_changed_lines(modified, original), (1, 1));
+}
+
+#[test]
+fn owned_patch() {
+    let text = String::from(
+        "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@ fn main() {
+ a
+-b
++B
+ c
+@@ -10 +10 @@
+-no newline
+\\ No newline at end of file
++newline
+",
+    );
+    let patch = Patch::from_str(&text).unwrap();
+    let expected = patch.to_string();
+    let stats = patch.stats();
+
+    let owned = patch.into_owned();
+    drop(text);
+
+    assert_eq!(owned.to_string(), expected);
+    assert_eq!(owned.as_patch().stats(), stats);
+    assert_eq!(owned.as_patch().original(), Some("a/src/lib.rs"));
+    assert_eq!(
+        owned.as_patch().hunks()[0].function_context(),
+        Some("fn main() {")
+    );
+    assert_eq!(owned.clone(), owned);
+}
+
+#[test]
+fn timeout() {
+    // Scattered changes over many lines make for a deep search
+    let original: String = (0..2000).map(|i| format!("{}\n", i)).collect();
+    let modified: String = (0..2000)
+        .map(|i| match i % 3 {
+            0 => format!("{}\n", i * 7),
+            _ => format!("{}\n", i),
+        })
+        .collect();
+
+    let full = DiffOptions::new().create_patch(&original, &modified);
+    let patch = DiffOptions::new()
+        .set_timeout(std::time::Duration::from_secs(60))
+        .try_create_patch(&original, &modified)
+        .unwrap();
+    assert_eq!(patch, full);
+
+    let timeout = DiffOptions::new()
+        .set_timeout(std::time::Duration::ZERO)
+        .try_create_patch(&original, &modified)
+        .unwrap_err();
+    assert_eq!(timeout.to_string(), "diff timed out");
+    let coarse = timeout.into_patch();
+    assert_eq!(coarse.hunks().len(), 1);
+    assert!(coarse.stats().deletions() > full.stats().deletions());
+    assert_eq!(apply(&original, &coarse).unwrap(), modified);
+}
+
+#[test]
+fn diff_cache() {
+    let mut cache = DiffCache::new(2);
+    let first = cache.get_or_compute("a\nb\n", "a\nc\n", 3).clone();
+    assert_eq!(
+        first.to_string(),
+        create_patch("a\nb\n", "a\nc\n").to_string()
+    );
+    assert_eq!(cache.get_or_compute("a\nb\n", "a\nc\n", 3), &first);
+    assert_eq!(cache.len(), 1);
+
+    // Swapped inputs, a different context length or different texts of the same length are
+    // all different entries
+    assert_ne!(cache.get_or_compute("a\nc\n", "a\nb\n", 3), &first);
+    assert_eq!(cache.len(), 2);
+    assert_ne!(cache.get_or_compute("a\nb\n", "a\nc\n", 0), &first);
+    assert_ne!(cache.get_or_compute("a\nb\n", "a\nd\n", 3), &first);
+
+    // Only the two most recently used patches are kept
+    assert_eq!(cache.len(), 2);
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn apply_file_creation_and_deletion() {
+    let creation = Patch::from_str(
+        "\
+--- /dev/null
++++ b/new
+@@ -0,0 +1,2 @@
++a
++b
+",
+    )
+    .unwrap();
+    assert_eq!(apply("", &creation).unwrap(), "a\nb\n");
+    assert_eq!(
+        apply("x\n", &creation).unwrap_err().to_string(),
+        "error applying patch: it creates a file but the base image isn't empty"
+    );
+    let (image, rejected) = apply_partial("x\n", &creation);
+    assert_eq!((image.as_str(), rejected.len()), ("x\n", 1));
+
+    let deletion = Patch::from_str(
+        "\
+--- a/old
++++ /dev/null
+@@ -1,2 +0,0 @@
+-a
+-b
+",
+    )
+    .unwrap();
+    assert_eq!(apply("a\nb\n", &deletion).unwrap(), "");
+    assert_eq!(
+        apply("a\nb\nc\n", &deletion).unwrap_err().to_string(),
+        "error applying patch: it deletes a file but not every line of the base image"
+    );
+
+    // Without the `/dev/null` headers the same ranges are an insertion at the start of the file
+    // and a deletion of its first lines, as produced without context
+    let insertion = DiffOptions::new()
+        .set_context_len(0)
+        .create_patch("c\n", "a\nb\nc\n");
+    assert_eq!(insertion.hunks()[0].old_range().len(), 0);
+    assert_eq!(apply("c\n", &insertion).unwrap(), "a\nb\nc\n");
+    let removal = DiffOptions::new()
+        .set_context_len(0)
+        .create_patch("a\nb\nc\n", "c\n");
+    assert_eq!(apply("a\nb\nc\n", &removal).unwrap(), "c\n");
+}
+
+#[cfg(feature = "experimental")]
+#[test]
+fn match_indentation() {
+    let original = "\
+fn f() {
+    a();
+    b();
+}
+";
+    let modified = "\
+fn f() {
+    if c {
+        a();
+        b();
+    }
+}
+";
+
+    // Ignoring whitespace changes hides that the calls moved into the new block
+    let mut opts = DiffOptions::new();
+    opts.set_ignore_space_change(true);
+    let stats = opts.create_patch(original, modified).stats();
+    assert_eq!((stats.deletions(), stats.insertions()), (0, 2));
+
+    opts.set_match_indentation(true);
+    let patch = opts.create_patch(original, modified);
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,6 @@
+ fn f() {
+-    a();
+-    b();
++    if c {
++        a();
++        b();
++    }
+ }
+";
+    assert_eq!(patch.to_string(), expected);
+
+    // Tabs and spaces reaching the same column match
+    assert!(opts
+        .create_patch("\ta();\n", "        a();\n")
+        .hunks()
+        .is_empty());
+}
+
+// Applies the subset of `ed` commands which `Patch::to_ed_script` produces
+fn apply_ed_script(base: &str, script: &str) -> String {
+    let mut lines: Vec<String> = base.lines().map(str::to_owned).collect();
+    let mut commands = script.lines();
+    let mut current = 0;
+
+    while let Some(command) = commands.next() {
+        if command == "s/.//" {
+            lines[current - 1].remove(0);
+            continue;
+        }
+
+        let (range, op) = command.split_at(command.len() - 1);
+        let (start, end): (usize, usize) = match range.split_once(',') {
+            Some((start, end)) => (start.parse().unwrap(), end.parse().unwrap()),
+            // Without an address the command applies to the current line
+            None if range.is_empty() => (current, current),
+            None => (range.parse().unwrap(), range.parse().unwrap()),
+        };
+        let mut at = match op {
+            "a" => end,
+            "c" | "d" => {
+                lines.drain(start - 1..end);
+                start - 1
+            }
+            _ => panic!("unexpected command {:?}", command),
+        };
+        current = at;
+        if op != "d" {
+            for line in commands.by_ref().take_while(|&line| line != ".") {
+                lines.insert(at, line.to_owned());
+                at += 1;
+            }
+            current = at;
+        }
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[test]
+fn ed_script() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "new\na\nc\nd\n.\nE\nf\ng\nh\ni\nj\n.\n";
+
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    let script = patch.to_ed_script();
+    let expected = "\
+10a
+..
+.
+s/.//
+a
+.
+5c
+..
+.
+s/.//
+a
+E
+.
+2d
+0a
+new
+.
+";
+    assert_eq!(script, expected);
+    assert_eq!(apply_ed_script(original, &script), modified);
+
+    let patch = create_patch(modified, original);
+    assert_eq!(apply_ed_script(modified, &patch.to_ed_script()), original);
+}
+
+#[test]
+fn verify_apply() {
+    use crate::apply::{verify_apply, VerifyError};
+
+    let base = "one\ntwo\nthree\nfour\n";
+    let patch = create_patch(base, "one\n2\nthree\nfour\n");
+    verify_apply(base, &patch, "one\n2\nthree\nfour\n").unwrap();
+
+    // A deliberately wrong expectation hands back how the actual result differs from it
+    let err = verify_apply(base, &patch, "one\n2\nthree\n4\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "patch result differs from the expected one"
+    );
+    let diff = match err {
+        VerifyError::Mismatch(diff) => diff,
+        VerifyError::Apply(e) => panic!("unexpected error: {}", e),
+    };
+    let expected = "\
+--- actual
++++ expected
+@@ -1,4 +1,4 @@
+ one
+ 2
+ three
+-four
++4
+";
+    assert_eq!(diff.to_string(), expected);
+
+    let err = verify_apply("unrelated\n", &patch, "").unwrap_err();
+    assert!(matches!(err, VerifyError::Apply(_)));
+    assert_eq!(err.to_string(), "error applying hunk #1");
+}
+
+#[test]
+fn patch_from_lines() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+    let modified = "one\n2\nthree\nfour\nfive\nsix\nseven\neight";
+    let old_lines: Vec<_> = original.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = modified.split_inclusive('\n').collect();
+
+    let mut opts = DiffOptions::new();
+    opts.set_context_len(1);
+    assert_eq!(
+        opts.create_patch_from_lines(&old_lines, &new_lines),
+        opts.create_patch(original, modified)
+    );
+
+    // Options comparing lines apply just the same
+    opts.set_ignore_space_change(true);
+    let new_lines: Vec<_> = new_lines
+        .iter()
+        .map(|line| line.replace("2", "two  "))
+        .collect();
+    let new_lines: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+    let patch = opts.create_patch_from_lines(&old_lines, &new_lines);
+    assert_eq!(patch.hunks().len(), 1);
+    assert_eq!(patch.hunks()[0].old_range().start(), 7);
+}
+
+#[test]
+fn wrap_at() {
+    let long = "x".repeat(200);
+    let modified = format!("a\n{}\n", long);
+    let patch = create_patch("a\n", &modified);
+    let f = PatchFormatter::new().with_wrap_at(80);
+
+    let expected = format!(
+        "\
+--- original
++++ modified
+@@ -1 +1,2 @@
+ a
++{}
++↳{}
++↳{}
+",
+        "x".repeat(79),
+        "x".repeat(78),
+        "x".repeat(43)
+    );
+    assert_eq!(f.fmt_patch(&patch).to_string(), expected);
+    let mut bytes = Vec::new();
+    f.write_patch_into(&patch, &mut bytes).unwrap();
+    assert_eq!(bytes, expected.as_bytes());
+    assert!(expected.lines().all(|row| row.chars().count() <= 80));
+
+    // Lines which fit aren't touched and chars are never split
+    let patch = create_patch("a\n", "ééé\n");
+    let f = PatchFormatter::new().with_wrap_at(3);
+    assert_eq!(
+        f.fmt_patch(&patch).to_string(),
+        "--- original\n+++ modified\n@@ -1 +1 @@\n-a\n+éé\n+↳é\n"
+    );
+}
+
+#[test]
+fn minimal() {
+    // Two unrelated texts drawn from a small alphabet have many short matches, so finding the
+    // shortest edit script takes many more steps than the non-minimal search allows
+    fn lines(mut seed: u32) -> String {
+        (0..3000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                format!("{}\n", (seed >> 16) % 4)
+            })
+            .collect()
+    }
+    let original = lines(1);
+    let modified = lines(2);
+
+    let mut opts = DiffOptions::new();
+    let (minimal_deleted, minimal_inserted) = opts.count_changed_lines(&original, &modified);
+    let minimal_patch = opts.create_patch(&original, &modified);
+    opts.set_minimal(false);
+    let (fast_deleted, fast_inserted) = opts.count_changed_lines(&original, &modified);
+    let fast_patch = opts.create_patch(&original, &modified);
+
+    assert!(minimal_deleted + minimal_inserted < fast_deleted + fast_inserted);
+    assert_eq!(apply(&original, &minimal_patch).unwrap(), modified);
+    assert_eq!(apply(&original, &fast_patch).unwrap(), modified);
+
+    // Small diffs are never cut short
+    let mut opts = DiffOptions::new();
+    opts.set_minimal(false);
+    assert_eq!(
+        opts.create_patch("a\nb\nc\n", "a\nB\nc\n"),
+        create_patch("a\nb\nc\n", "a\nB\nc\n")
+    );
+}
+
+#[test]
+fn text_edits() {
+    fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+        let mut text = text.to_owned();
+        for edit in edits.iter().rev() {
+            text.replace_range(edit.range(), edit.new_text());
+        }
+        text
+    }
+
+    let original = "fn main() {\n    println!(\"hello\");\n}\n";
+    let modified = "fn main() {\n    eprintln!(\"hello, world\");\n}\n// done\n";
+    let edits = DiffOptions::new().text_edits(original, modified);
+    assert_eq!(apply_edits(original, &edits), modified);
+    assert_eq!(
+        edits
+            .iter()
+            .map(|edit| (edit.range(), edit.new_text()))
+            .collect::<Vec<_>>(),
+        [(16..16, "e"), (31..31, ", world"), (37..37, "// done\n")]
+    );
+
+    // A deletion directly followed by an insertion is a single replacement and multi-byte chars
+    // are never split
+    let edits = DiffOptions::new().text_edits("naïve café", "naive cafè");
+    assert_eq!(
+        edits
+            .iter()
+            .map(|edit| (edit.range(), edit.new_text()))
+            .collect::<Vec<_>>(),
+        [(2..4, "i"), (10..12, "è")]
+    );
+    assert_eq!(apply_edits("naïve café", &edits), "naive cafè");
+
+    assert!(DiffOptions::new().text_edits("same", "same").is_empty());
+    let edits = DiffOptions::new().text_edits("", "new");
+    assert_eq!(apply_edits("", &edits), "new");
+}
+
+#[test]
+fn count_hidden_changes() {
+    let original = "one\ntwo  words\n3\n4\n5\n6\n7\n8\n9\nten\n";
+    let modified = "one\ntwo words\n3\n4\n5\n6\n7\n8\n9\nTEN\n";
+
+    let (patch, hidden) = DiffOptions::new().create_patch_counting_hidden(original, modified);
+    assert_eq!((patch.hunks().len(), hidden), (2, 0));
+
+    let mut opts = DiffOptions::new();
+    opts.set_ignore_space_change(true);
+    let (patch, hidden) = opts.create_patch_counting_hidden(original, modified);
+    assert_eq!(patch, opts.create_patch(original, modified));
+    assert_eq!((patch.hunks().len(), hidden), (1, 1));
+
+    // A change which is only partially hidden still shows up in the patch
+    let (_, hidden) = opts.create_patch_counting_hidden("a  b\nc\n", "a b\nC\n");
+    assert_eq!(hidden, 0);
+
+    let (_, hidden) = opts.create_patch_counting_hidden(original, original);
+    assert_eq!(hidden, 0);
+}
+
+#[test]
+fn first_hunk_leading_context() {
+    let original = "one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+    let modified = "one\n2\nthree\nfour\nfive\nsix\nseven\n";
+
+    // Only one line precedes the change, so that's all the leading context there is
+    let patch = create_patch(original, modified);
+    let expected = "\
+--- original
++++ modified
+@@ -1,5 +1,5 @@
+ one
+-two
++2
+ three
+ four
+ five
+";
+    assert_eq!(patch.to_string(), expected);
+
+    // Lines inserted before the change don't shift where the leading context starts
+    let modified = "zero\none\n2\nthree\nfour\nfive\nsix\nseven\n";
+    let patch = create_patch(original, modified);
+    let expected = "\
+--- original
++++ modified
+@@ -1,5 +1,6 @@
++zero
+ one
+-two
++2
+ three
+ four
+ five
+";
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+}
+
+#[test]
+fn hunk_line_ranges() {
+    fn zero_based(range: HunkRange) -> std::ops::Range<usize> {
+        let start = if range.is_empty() {
+            range.start()
+        } else {
+            range.start() - 1
+        };
+        start..start + range.len()
+    }
+
+    let original: String = (0..40).map(|i| format!("{}\n", i)).collect();
+    let modified: String = (0..40)
+        .filter(|i| i % 13 != 5)
+        .flat_map(|i| match i {
+            0 | 20 | 21 => vec![format!("new {}\n", i), format!("{}\n", i)],
+            _ => vec![format!("{}\n", i)],
+        })
+        .collect();
+
+    for context_len in [0, 1, 3, 10] {
+        let mut opts = DiffOptions::new();
+        opts.set_context_len(context_len);
+        for (a, b) in [(&original, &modified), (&modified, &original)] {
+            let expected: Vec<_> = opts
+                .create_patch(a, b)
+                .hunks()
+                .iter()
+                .map(|hunk| (zero_based(hunk.old_range()), zero_based(hunk.new_range())))
+                .collect();
+            assert_eq!(opts.hunk_line_ranges(a, b), expected);
+        }
+    }
+    assert!(DiffOptions::new().hunk_line_ranges("a\n", "a\n").is_empty());
+}
+
+#[test]
+fn deletes_before_inserts() {
+    // Every string over "ab" of up to 4 characters
+    let texts: Vec<Vec<u8>> = (0..=4)
+        .flat_map(|len| {
+            (0..1 << len).map(move |bits: u32| {
+                (0..len)
+                    .map(|i| if bits & (1 << i) == 0 { b'a' } else { b'b' })
+                    .collect()
+            })
+        })
+        .collect();
+
+    for old in &texts {
+        for new in &texts {
+            let solution = DiffOptions::default().diff_slice(old, new);
+            // Compaction only moves changes around
+            assert_eq!(&reconstruct_old(&solution).concat(), old);
+            assert_eq!(&reconstruct_new(&solution).concat(), new);
+            for pair in solution.windows(2) {
+                assert!(
+                    !matches!(pair, [DiffRange::Insert(_), DiffRange::Delete(_)]),
+                    "insert before delete diffing {:?} against {:?}",
+                    String::from_utf8_lossy(old),
+                    String::from_utf8_lossy(new),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn apply_ignoring_trailing_whitespace() {
+    let base_image = "\
+fn main() {  \r
+    let x = 1;\t\r
+    println!(\"{}\", x);\r
+}   \r
+";
+    let modified = "\
+fn main() {  \r
+    let x = 2;\r
+    println!(\"{}\", x);\r
+}   \r
+";
+    // The patch as it looks after its trailing whitespace was stripped, CRs included
+    let patch = create_patch(base_image, modified).to_string();
+    let stripped: String = patch
+        .lines()
+        .map(|line| format!("{}\n", line.trim_end()))
+        .collect();
+    let patch = Patch::from_str(&stripped).unwrap();
+
+    assert!(apply(base_image, &patch).is_err());
+
+    let mut opts = ApplyOptions::new();
+    opts.set_ignore_trailing_whitespace(true);
+    // The context lines keep their trailing whitespace and the inserted line gets the base
+    // image's line ending
+    assert_eq!(opts.apply(base_image, &patch).unwrap(), modified);
+    let (image, rejected) = opts.apply_partial(base_image, &patch);
+    assert_eq!(image, modified);
+    assert!(rejected.is_empty());
+
+    // Leading whitespace still has to match
+    let indented = base_image.replace("    let", "  let");
+    assert!(opts.apply(&indented, &patch).is_err());
+}
+
+#[test]
+fn indexed_diff() {
+    let solution = diff("The quick brown fox", "The slow brown cat");
+    assert_eq!(
+        solution,
+        [
+            Diff::Equal("The "),
+            Diff::Delete("quick"),
+            Diff::Insert("slow"),
+            Diff::Equal(" brown "),
+            Diff::Delete("fox"),
+            Diff::Insert("cat"),
+        ]
+    );
+
+    let indices: Vec<_> = indexed(&solution).map(|(old, new, _)| (old, new)).collect();
+    assert_eq!(
+        indices,
+        [(0, 0), (4, 4), (9, 4), (9, 8), (16, 15), (19, 15)]
+    );
+
+    // The indices point at the diff's text in its input
+    for (old, new, diff) in indexed(&solution) {
+        match *diff {
+            Diff::Equal(s) | Diff::Delete(s) => {
+                assert!("The quick brown fox"[old..].starts_with(s))
+            }
+            Diff::Insert(s) => assert!("The slow brown cat"[new..].starts_with(s)),
+        }
+    }
+}
+
+#[test]
+fn extend_patch() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+    let edits = [
+        "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nL\nM\n",
+        "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n",
+        "a\nb\nc\nd\ne\nf\nh\ni\nj\nk\nl\n",
+    ];
+
+    let mut opts = DiffOptions::new();
+    opts.set_context_len(1);
+
+    let mut patch = Patch::from_str("").unwrap();
+    for modified in edits {
+        patch.extend(opts.create_patch(original, modified));
+    }
+    assert_eq!(patch.hunks().len(), 3);
+    // The hunks are appended as they are, so they're still out of order
+    assert!(patch.validate().is_err());
+
+    patch.sort_hunks();
+    patch.renumber();
+    patch.validate().unwrap();
+    assert_eq!(
+        apply(original, &patch).unwrap(),
+        "A\nb\nc\nd\ne\nf\nh\ni\nj\nk\nL\nM\n"
+    );
+}
+
+#[test]
+fn diff_adaptive() {
+    let original = "\
+The quick brown fox
+jumps over the lazy dog.
+Pack my box with five dozen liquor jugs.
+How vexingly quick daft zebras jump!
+";
+    let modified = "\
+The quick brown fox
+jumps over the lazy cog.
+Pack my box with five dozen liquor jugs.
+Sphinx of black quartz, judge my vow.
+";
+    let regions = DiffOptions::new().diff_adaptive(original, modified);
+    let regions: Vec<_> = regions
+        .iter()
+        .map(|region| (region.granularity(), region.diffs()))
+        .collect();
+
+    assert_eq!(
+        regions,
+        [
+            (
+                Granularity::Line,
+                &[Diff::Equal("The quick brown fox\n")][..]
+            ),
+            // A typo fix is refined down to the changed character
+            (
+                Granularity::Char,
+                &[
+                    Diff::Equal("jumps over the lazy "),
+                    Diff::Delete("d"),
+                    Diff::Insert("c"),
+                    Diff::Equal("og.\n"),
+                ]
+            ),
+            (
+                Granularity::Line,
+                &[Diff::Equal("Pack my box with five dozen liquor jugs.\n")]
+            ),
+            // A rewritten line is replaced as a whole
+            (
+                Granularity::Line,
+                &[
+                    Diff::Delete("How vexingly quick daft zebras jump!\n"),
+                    Diff::Insert("Sphinx of black quartz, judge my vow.\n"),
+                ]
+            ),
+        ]
+    );
+
+    // Pure insertions and deletions of lines aren't refined
+    let regions = DiffOptions::new().diff_adaptive("a\nb\n", "a\nb\nc\n");
+    assert_eq!(regions[1].granularity(), Granularity::Line);
+    assert_eq!(regions[1].diffs(), [Diff::Insert("c\n")]);
+
+    // The regions put back together give both texts
+    let regions = DiffOptions::new().diff_adaptive(original, modified);
+    let (mut old, mut new) = (String::new(), String::new());
+    for diff in regions.iter().flat_map(|region| region.diffs()) {
+        match *diff {
+            Diff::Equal(text) => {
+                old.push_str(text);
+                new.push_str(text);
+            }
+            Diff::Delete(text) => old.push_str(text),
+            Diff::Insert(text) => new.push_str(text),
+        }
+    }
+    assert_eq!((old.as_str(), new.as_str()), (original, modified));
+}
+
+#[test]
+fn owned_diff() {
+    let original = String::from("The quick brown fox\njumps over\n");
+    let modified = String::from("The quick red fox\njumps over\nthe lazy dog\n");
+    let borrowed: Vec<Vec<Diff<'_, str>>> = DiffOptions::new()
+        .diff_adaptive(&original, &modified)
+        .iter()
+        .map(|region| region.diffs().to_vec())
+        .collect();
+    let owned: Vec<Vec<OwnedDiff>> = borrowed
+        .iter()
+        .map(|diffs| OwnedDiff::from_diffs(diffs))
+        .collect();
+    assert_eq!(owned[0][0], Diff::Equal("The quick ").to_owned());
+    assert_eq!(
+        OwnedDiff::from(Diff::Insert("red")),
+        OwnedDiff::Insert("red".to_owned())
+    );
+
+    // Remember the texts, then drop everything the diff was borrowing from
+    let expected: Vec<Vec<(String, &str)>> = borrowed
+        .iter()
+        .map(|diffs| {
+            diffs
+                .iter()
+                .map(|diff| match diff {
+                    Diff::Equal(text) => (text.to_string(), "="),
+                    Diff::Delete(text) => (text.to_string(), "-"),
+                    Diff::Insert(text) => (text.to_string(), "+"),
+                })
+                .collect()
+        })
+        .collect();
+    drop(borrowed);
+    drop((original, modified));
+
+    // The owned diff can be moved to another thread
+    let reconstructed = std::thread::spawn(move || {
+        owned
+            .iter()
+            .map(|diffs| {
+                diffs
+                    .iter()
+                    .map(|diff| match diff.as_diff() {
+                        Diff::Equal(text) => (text.to_owned(), "="),
+                        Diff::Delete(text) => (text.to_owned(), "-"),
+                        Diff::Insert(text) => (text.to_owned(), "+"),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(reconstructed, expected);
+}
+
+#[test]
+fn max_input_bytes() {
+    // Entirely different texts, which would take a long time to diff
+    let original = "a\n".repeat(1 << 20);
+    let modified: String = (0..1 << 18).map(|i| format!("{}\n", i)).collect();
+
+    let mut opts = DiffOptions::new();
+    opts.set_max_input_bytes(1 << 20);
+    let error = opts.checked_create_patch(&original, &modified).unwrap_err();
+    assert_eq!(error.input_bytes(), original.len() + modified.len());
+    assert_eq!(error.max_input_bytes(), 1 << 20);
+    assert!(opts
+        .checked_create_patch_bytes(original.as_bytes(), modified.as_bytes())
+        .is_err());
+
+    // The limit is inclusive
+    opts.set_max_input_bytes(8);
+    assert!(opts.checked_create_patch("a\nb\n", "a\nc\n").is_ok());
+    assert!(opts.checked_create_patch("a\nb\n", "a\nc\nd\n").is_err());
+    assert!(DiffOptions::new()
+        .checked_create_patch("a\nb\n", "a\nc\nd\n")
+        .is_ok());
+}
+
+#[test]
+fn char_level_limits() {
+    fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+        let mut text = text.to_owned();
+        for edit in edits.iter().rev() {
+            text.replace_range(edit.range(), edit.new_text());
+        }
+        text
+    }
+
+    // Too large texts are compared by lines, and runs of changed lines which are too large are
+    // replaced whole
+    let original = "a\nbc\nx\nhello world\nd\n";
+    let modified = "a\nBC\nx\nhello there\nd\n";
+    let mut opts = DiffOptions::new();
+    opts.set_max_input_bytes(8);
+    let edits = opts.text_edits(original, modified);
+    assert_eq!(
+        edits
+            .iter()
+            .map(|edit| (edit.range(), edit.new_text()))
+            .collect::<Vec<_>>(),
+        [(2..4, "BC"), (7..19, "hello there\n")]
+    );
+    assert_eq!(apply_edits(original, &edits), modified);
+    assert_eq!(
+        opts.edit_ops(original, modified),
+        [
+            EditOp::Delete {
+                position: 2,
+                len: 2
+            },
+            EditOp::Insert {
+                position: 4,
+                text: "BC"
+            },
+            EditOp::Delete {
+                position: 7,
+                len: 12
+            },
+            EditOp::Insert {
+                position: 19,
+                text: "hello there\n"
+            },
+        ]
+    );
+    let regions = opts.diff_adaptive("a\nhello world\n", "a\nhello there\n");
+    assert_eq!(regions[1].granularity(), Granularity::Line);
+    assert_eq!(
+        regions[1].diffs(),
+        [Diff::Delete("hello world\n"), Diff::Insert("hello there\n")]
+    );
+
+    // Scattered changes make for a deep search, which gives up once the timeout is reached
+    let original: String = (0..2000).map(|i| format!("{}\n", i)).collect();
+    let modified: String = (0..2000)
+        .map(|i| match i % 3 {
+            0 => format!("{}\n", i * 7),
+            _ => format!("{}\n", i),
+        })
+        .collect();
+    let replaced = |edits: &[TextEdit]| edits.iter().map(|edit| edit.range().len()).sum::<usize>();
+    let full = DiffOptions::new().text_edits(&original, &modified);
+    let coarse = DiffOptions::new()
+        .set_timeout(std::time::Duration::ZERO)
+        .text_edits(&original, &modified);
+    assert!(replaced(&coarse) > replaced(&full));
+    assert_eq!(apply_edits(&original, &coarse), modified);
+}
+
+#[test]
+fn long_lines_are_not_diffed_by_chars() {
+    // A minified file, with a change every few chars of its single line
+    let original: String = (0..1 << 20).map(|i| format!("{};", i % 10)).collect();
+    let modified = original.replace('7', "8");
+    let (original, modified) = (original.as_str(), modified.as_str());
+
+    let edits = DiffOptions::new().text_edits(original, modified);
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].range(), 0..original.len());
+    assert_eq!(edits[0].new_text(), modified);
+    assert_eq!(
+        DiffOptions::new().edit_ops(original, modified),
+        [
+            EditOp::Delete {
+                position: 0,
+                len: original.len()
+            },
+            EditOp::Insert {
+                position: original.len(),
+                text: modified
+            },
+        ]
+    );
+    let regions = DiffOptions::new().diff_adaptive(original, modified);
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].granularity(), Granularity::Line);
+
+    let patch = create_patch(original, modified);
+    let f = PatchFormatter::new().with_word_diff();
+    let formatted = f.fmt_patch(&patch).to_string();
+    assert!(formatted.contains(&format!("\n-{}\n", original)));
+    assert!(formatted.contains(&format!("\n+{}\n", modified)));
+    assert!(!formatted.contains("[-"));
+
+    // Short changed lines are still compared by chars, unless the limit is lowered
+    let mut opts = DiffOptions::new();
+    assert_eq!(opts.text_edits("a\nbcd\n", "a\nbxd\n").len(), 1);
+    assert_eq!(opts.text_edits("a\nbcd\n", "a\nbxd\n")[0].range(), 3..4);
+    opts.set_max_char_diff_bytes(6);
+    assert_eq!(opts.text_edits("a\nbcd\n", "a\nbxd\n")[0].range(), 2..6);
+    let regions = opts.diff_adaptive("a\nbcd\n", "a\nbxd\n");
+    assert_eq!(regions[1].granularity(), Granularity::Line);
+}
+
+#[test]
+fn suggestions() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n";
+    let modified = "1\n2\n3\n4\nfive\nsix\nseven\n8\n9\n10\n11\n12\n13\n14\n15\nsixteen\n";
+    let patch = create_patch(original, modified);
+    let suggestions = patch.to_suggestions();
+    assert_eq!(suggestions.len(), 2);
+
+    // Lines 5-7 are replaced
+    assert_eq!(suggestions[0].old_range().range(), 5..8);
+    assert_eq!(suggestions[0].replacement(), "five\nsix\nseven\n");
+    assert_eq!(
+        suggestions[0].to_string(),
+        "```suggestion\nfive\nsix\nseven\n```\n"
+    );
+
+    // An insertion at the end of the file
+    assert!(suggestions[1].old_range().is_empty());
+    assert_eq!(suggestions[1].old_range().start(), 15);
+    assert_eq!(suggestions[1].replacement(), "sixteen\n");
+
+    // A deletion suggests an empty block
+    let patch = create_patch("a\nb\nc\n", "a\nc\n");
+    let suggestions = patch.to_suggestions();
+    assert_eq!(suggestions[0].old_range().range(), 2..3);
+    assert_eq!(suggestions[0].to_string(), "```suggestion\n```\n");
+
+    // Backticks in the replacement get a longer fence and a missing newline is added
+    let patch = create_patch("a\nb", "a\n```rust");
+    assert_eq!(
+        patch.to_suggestions()[0].to_string(),
+        "````suggestion\n```rust\n````\n"
+    );
+}
+
+#[test]
+fn ignore_whitespace_only_hunks() {
+    let original = "\
+fn main() {
+    let x = 1;
+    let y = 2;
+}
+
+fn helper() {
+    println!(\"hi\");
+}
+
+fn other() {
+    1 + 1
+}
+";
+    let modified = "\
+fn main() {
+\tlet x = 1;
+\tlet y = 2;
+}
+
+fn helper() {
+    println!(\"hello\");
+}
+
+fn other() {
+    1+1
+}
+";
+    let mut opts = DiffOptions::new();
+    opts.set_context_len(1);
+    assert_eq!(opts.create_patch(original, modified).hunks().len(), 3);
+
+    opts.set_ignore_whitespace_only_hunks(true);
+    let expected = "\
+--- original
++++ modified
+@@ -6,3 +6,3 @@
+ fn helper() {
+-    println!(\"hi\");
++    println!(\"hello\");
+ }
+";
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(
+        opts.create_patch_bytes(original.as_bytes(), modified.as_bytes())
+            .to_bytes(),
+        expected.as_bytes()
+    );
+    assert_eq!(opts.hunk_line_ranges(original, modified), [(5..8, 5..8)]);
+
+    let (_, hidden) = opts.create_patch_counting_hidden(original, modified);
+    assert_eq!(hidden, 2);
+}
+
+#[test]
+fn inline_changes() {
+    let original = "\
+let x = foo(1);
+unchanged
+naïve café
+last line
+";
+    let modified = "\
+let x = bar(1);
+unchanged
+naïve cafè
+added line
+last line";
+    let patch = create_patch(original, modified);
+
+    let f = PatchFormatter::new().with_inline_changes();
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,5 @@
+ let x = {foo → bar}(1);
+ unchanged
+ naïve caf{é → è}
+ {last → added} line
++last line
+\\ No newline at end of file
+";
+    assert_eq!(f.fmt_patch(&patch).to_string(), expected);
+
+    let mut bytes = Vec::new();
+    f.write_patch_into(&patch, &mut bytes).unwrap();
+    assert_eq!(bytes, expected.as_bytes());
+
+    // Pure insertions and deletions and a missing newline
+    let patch = create_patch("abc\n", "abcabc");
+    let expected = "\
+--- original
++++ modified
+@@ -1 +1 @@
+ abc{ → abc}
+\\ No newline at end of file
+";
+    assert_eq!(f.fmt_patch(&patch).to_string(), expected);
+}
+
+#[test]
+fn edit_ops() {
+    // Applies the ops from first to last, shifting their positions by the earlier ops
+    fn apply_ops(original: &str, ops: &[EditOp<'_>]) -> String {
+        let mut text = original.to_owned();
+        let mut shift = 0isize;
+        let mut last_position = 0;
+        for op in ops {
+            match *op {
+                EditOp::Insert {
+                    position,
+                    text: inserted,
+                } => {
+                    assert!(position >= last_position);
+                    last_position = position;
+                    let at = (position as isize + shift) as usize;
+                    text.insert_str(at, inserted);
+                    shift += inserted.len() as isize;
+                }
+                EditOp::Delete { position, len } => {
+                    assert!(position >= last_position);
+                    last_position = position + len;
+                    let at = (position as isize + shift) as usize;
+                    text.replace_range(at..at + len, "");
+                    shift -= len as isize;
+                }
+            }
+        }
+        text
+    }
+
+    let cases = [
+        ("The quick brown fox", "The slow brown dog"),
+        ("", "inserted"),
+        ("deleted", ""),
+        ("same", "same"),
+        ("naïve café", "naive cafés"),
+        ("a\nb\nc\n", "a\nc\nd\n"),
+    ];
+    for (original, modified) in cases {
+        let ops = DiffOptions::new().edit_ops(original, modified);
+        assert_eq!(apply_ops(original, &ops), modified);
+    }
+
+    // Chars sharing some of their bytes may be split across several byte-level changes
+    let chars = ["a", "é", "è", "€", "—"];
+    let mut texts = vec![String::new()];
+    for len in 0..3 {
+        for i in 0..texts.len() {
+            if texts[i].chars().count() == len {
+                for c in chars {
+                    let text = texts[i].clone() + c;
+                    texts.push(text);
+                }
+            }
+        }
+    }
+    for original in &texts {
+        for modified in &texts {
+            let ops = DiffOptions::new().edit_ops(original, modified);
+            assert_eq!(&apply_ops(original, &ops), modified);
+        }
+    }
+
+    // The ops describe the same changes as the text edits
+    let edits_as_ops = |original, modified| {
+        let mut ops = Vec::new();
+        for edit in DiffOptions::new().text_edits(original, modified) {
+            if !edit.range().is_empty() {
+                ops.push((edit.range().start, edit.range().len(), String::new()));
+            }
+            if !edit.new_text().is_empty() {
+                ops.push((edit.range().end, 0, edit.new_text().to_owned()));
+            }
+        }
+        ops
+    };
+    for (original, modified) in cases
+        .into_iter()
+        .chain([("a\nb\n\na\nb\n", "a\nb\n\na\nb\n\na\nb\n")])
+        .chain(texts.iter().flat_map(|original| {
+            texts
+                .iter()
+                .map(move |modified| (original.as_str(), modified.as_str()))
+        }))
+    {
+        let ops = DiffOptions::new()
+            .edit_ops(original, modified)
+            .into_iter()
+            .map(|op| match op {
+                EditOp::Insert { position, text } => (position, 0, text.to_owned()),
+                EditOp::Delete { position, len } => (position, len, String::new()),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ops, edits_as_ops(original, modified));
+    }
+
+    let ops = DiffOptions::new().edit_ops("The quick brown fox", "The slow brown cat");
+    assert_eq!(
+        ops,
+        [
+            EditOp::Delete {
+                position: 4,
+                len: 5
+            },
+            EditOp::Insert {
+                position: 9,
+                text: "slow"
+            },
+            EditOp::Delete {
+                position: 16,
+                len: 3
+            },
+            EditOp::Insert {
+                position: 19,
+                text: "cat"
+            },
+        ]
+    );
+}
+
+#[test]
+fn context_from_unchanged_side() {
+    let original = "fn  main() {\n    removed();\n    kept();\n}\n";
+    let modified = "fn main() {\n    kept();\n}\n";
+    let mut opts = DiffOptions::new();
+    opts.set_ignore_space_change(true);
+
+    // A hunk which only deletes lines takes its context from the old file, so it applies to it
+    let patch = opts.create_patch(original, modified);
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,3 @@
+ fn  main() {
+-    removed();
+     kept();
+ }
+";
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(
+        apply(original, &patch).unwrap(),
+        "fn  main() {\n    kept();\n}\n"
+    );
+    let patch = opts.create_patch_bytes(original.as_bytes(), modified.as_bytes());
+    assert_eq!(patch.to_bytes(), expected.as_bytes());
+
+    // Any other hunk takes it from the new file
+    let original = "fn  main() {\n    kept();\n}\n";
+    let modified = "fn main() {\n    added();\n    kept();\n}\n";
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,4 @@
+ fn main() {
++    added();
+     kept();
+ }
+";
+    assert_eq!(opts.create_patch(original, modified).to_string(), expected);
+}
+
+#[test]
+fn diff_builder() {
+    let old_main = "fn main() {\n    println!(\"hello\");\n}\n";
+    let new_main = "fn main() {\n    println!(\"hello, world\");\n}\n";
+    let readme = "# diffy\n";
+    let old_lib = "pub mod diff;\n";
+    let new_lib = "pub mod diff;\npub mod patch;\n";
+
+    let builder = || {
+        DiffBuilder::new()
+            .add_file("src/main.rs", old_main, new_main, 1)
+            .add_file("README.md", readme, readme, 3)
+            .add_file("src/lib.rs", old_lib, new_lib, 3)
+    };
+
+    let patches = builder().build();
+    let expected = "\
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    println!(\"hello\");
++    println!(\"hello, world\");
+ }
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1,2 @@
+ pub mod diff;
++pub mod patch;
+";
+    assert_eq!(patches.len(), 2);
+    assert_eq!(patches.to_string(), expected);
+
+    assert_eq!(apply(old_main, &patches.patches()[0]).unwrap(), new_main);
+    assert_eq!(apply(old_lib, &patches.patches()[1]).unwrap(), new_lib);
+
+    let patches = builder().include_unchanged(true).build();
+    assert_eq!(patches.len(), 3);
+    assert_eq!(patches.patches()[1].original(), Some("a/README.md"));
+    assert!(patches.patches()[1].hunks().is_empty());
+    assert_eq!(
+        patches.to_string(),
+        expected.replace(
+            "--- a/src/lib.rs",
+            "--- a/README.md\n+++ b/README.md\n--- a/src/lib.rs"
+        )
+    );
+
+    assert!(DiffBuilder::new().build().is_empty());
+
+    // Created and deleted files are diffed against /dev/null
+    let patches = DiffBuilder::new()
+        .add_file("new.txt", "", "new\n", 3)
+        .add_file("old.txt", "old\n", "", 3)
+        .add_file("empty.txt", "", "", 3)
+        .include_unchanged(true)
+        .build();
+    let names: Vec<_> = patches
+        .patches()
+        .iter()
+        .map(|patch| (patch.original(), patch.modified()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            (Some("/dev/null"), Some("b/new.txt")),
+            (Some("a/old.txt"), Some("/dev/null")),
+            (Some("a/empty.txt"), Some("b/empty.txt")),
+        ]
+    );
+    assert_eq!(apply("", &patches.patches()[0]).unwrap(), "new\n");
+    assert_eq!(apply("old\n", &patches.patches()[1]).unwrap(), "");
+}
+
+#[test]
+fn apply_hunks_one_at_a_time() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let modified = "1\none\nuno\n2\n3\n4\n5\n6\n7\n9\n10\n";
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+    let lines = || -> Vec<String> { original.split_inclusive('\n').map(String::from).collect() };
+
+    // The first hunk inserts two lines, shifting the second one down
+    let mut buffer = lines();
+    apply_hunk(&mut buffer, &patch.hunks()[0], 0).unwrap();
+    let first = &patch.hunks()[0];
+    let offset = first.new_range().len() as isize - first.old_range().len() as isize;
+    assert_eq!(offset, 2);
+    // Without the offset the hunk doesn't match, which leaves the buffer untouched
+    let error = apply_hunk(&mut buffer, &patch.hunks()[1], 0).unwrap_err();
+    assert_eq!(error.to_string(), "error applying hunk at line 7");
+    apply_hunk(&mut buffer, &patch.hunks()[1], offset).unwrap();
+    assert_eq!(buffer.concat(), modified);
+
+    // Picking only the second hunk applies it where it is
+    let mut buffer = lines();
+    apply_hunk(&mut buffer, &patch.hunks()[1], 0).unwrap();
+    assert_eq!(buffer.concat(), original.replace("8\n", ""));
+
+    // Out of bounds in either direction
+    let error = apply_hunk(&mut lines(), &patch.hunks()[0], -5).unwrap_err();
+    assert_eq!(error.to_string(), "error applying hunk at line -4");
+    assert!(apply_hunk(&mut lines(), &patch.hunks()[1], 5).is_err());
+
+    // Offsets which overflow report the hunk's own line
+    let error = apply_hunk(&mut lines(), &patch.hunks()[1], isize::MAX).unwrap_err();
+    assert_eq!(error.to_string(), "error applying hunk at line 7");
+    assert!(apply_hunk(&mut lines(), &patch.hunks()[1], isize::MIN).is_err());
+    let huge = format!("@@ -{},1 +1 @@\n-1\n+one\n", usize::MAX);
+    let huge = Patch::from_str(&huge).unwrap();
+    for offset in [0, -1, isize::MIN, isize::MAX] {
+        let mut buffer = lines();
+        assert!(apply_hunk(&mut buffer, &huge.hunks()[0], offset).is_err());
+        assert_eq!(buffer, lines());
+    }
+}
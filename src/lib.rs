@@ -219,7 +219,20 @@ mod patch;
 mod range;
 mod utils;
 
-pub use apply::{apply, apply_bytes, ApplyError};
-pub use diff::{create_patch, create_patch_bytes, DiffOptions};
-pub use merge::{merge, merge_bytes, ConflictStyle, MergeOptions};
-pub use patch::{Hunk, HunkRange, Line, ParsePatchError, Patch, PatchFormatter};
+pub use apply::{
+    apply, apply_bytes, apply_bytes_partial, apply_hunk, apply_partial, verify_apply, ApplyError,
+    ApplyOptions, VerifyError,
+};
+pub use diff::{
+    create_patch, create_patch_bytes, indexed, lcs, lcs_by_key, AdaptiveRegion, Diff, DiffBuilder,
+    DiffCache, DiffOptions, EditOp, EditRange, Granularity, HintError, InputTooLarge, OwnedDiff,
+    TextEdit, Timeout,
+};
+pub use merge::{
+    merge, merge_bytes, merge_with_provenance, ConflictStyle, MergeOptions, MergedLine, Origin,
+};
+pub use patch::{
+    DiffStats, Hunk, HunkDivergence, HunkRange, IndexLine, Line, LineKind, OwnedPatch,
+    ParsePatchError, Patch, PatchFormatter, PatchSet, RepairError, Suggestion,
+};
+pub use utils::LineEnding;
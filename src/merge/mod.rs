@@ -1,9 +1,9 @@
 use crate::{
     diff::DiffOptions,
     range::{DiffRange, Range, SliceLike},
-    utils::Classifier,
+    utils::{split_words, without_cr, Classifier, LineEnding, LineIter, Text},
 };
-use std::{cmp, fmt};
+use std::{borrow::Cow, cmp, fmt};
 
 #[cfg(test)]
 mod tests;
@@ -110,6 +110,72 @@ pub enum ConflictStyle {
     /// >>>>>>> B
     /// ```
     Diff3,
+
+    /// Same as `Diff3` except that lines which were changed identically on both sides at the
+    /// start or the end of a conflict are moved out of the conflict markers, similar to git's
+    /// `zdiff3` conflict style.
+    ///
+    /// ```console
+    /// lines changed identically in files A and B
+    /// <<<<<<< A
+    /// lines in file A
+    /// ||||||| Original
+    /// lines in Original file
+    /// =======
+    /// lines in file B
+    /// >>>>>>> B
+    /// lines changed identically in files A and B
+    /// ```
+    ZealousDiff3,
+}
+
+/// Where a line in the result of a merge came from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// The line is unchanged in all three files
+    Ancestor,
+    /// The line was only changed in our file
+    Ours,
+    /// The line was only changed in their file
+    Theirs,
+    /// The line was changed identically in both files
+    Both,
+    /// The line is our side of a conflict
+    ConflictOurs,
+    /// The line is the ancestor's side of a conflict
+    ConflictAncestor,
+    /// The line is their side of a conflict
+    ConflictTheirs,
+}
+
+/// A line in the result of a merge along with where it came from
+///
+/// The lines of a conflicting region are emitted as our lines, followed by the ancestor's lines,
+/// followed by their lines, without any conflict markers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MergedLine<'a, T: ?Sized> {
+    content: &'a T,
+    origin: Origin,
+}
+
+impl<T: ?Sized> Copy for MergedLine<'_, T> {}
+
+impl<T: ?Sized> Clone for MergedLine<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ?Sized> MergedLine<'a, T> {
+    /// Returns the content of the line, including its line ending
+    pub fn content(&self) -> &'a T {
+        self.content
+    }
+
+    /// Returns where the line came from
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
 }
 
 /// A collection of options for modifying the way a merge is performed
@@ -117,6 +183,8 @@ pub enum ConflictStyle {
 pub struct MergeOptions {
     conflict_marker_length: usize,
     style: ConflictStyle,
+    line_ending: LineEnding,
+    word_level: bool,
 }
 
 impl MergeOptions {
@@ -125,10 +193,14 @@ impl MergeOptions {
     /// ## Defaults
     /// * conflict_marker_length = 7
     /// * style = ConflictStyle::Diff3
+    /// * line_ending = LineEnding::Auto
+    /// * word_level = false
     pub fn new() -> Self {
         Self {
             conflict_marker_length: DEFAULT_CONFLICT_MARKER_LENGTH,
             style: ConflictStyle::Diff3,
+            line_ending: LineEnding::Auto,
+            word_level: false,
         }
     }
 
@@ -144,6 +216,48 @@ impl MergeOptions {
         self
     }
 
+    /// Set how line endings are treated, see [`LineEnding`]
+    ///
+    /// The ancestor decides the line ending when it is `LineEnding::Auto`. A side which only
+    /// changed the line ending of some lines, e.g. by converting the whole file, still changed
+    /// them, and the lines of a side which mostly uses the other line ending are written as they
+    /// are. Conflict markers are written with the chosen line ending. When reporting provenance the lines are returned as
+    /// they are and only the comparison is affected.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) -> &mut Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Enable/Disable resolving conflicts within a single line word by word
+    ///
+    /// When both sides changed the same line, the line is split into runs of whitespace and
+    /// non-whitespace like [`PatchFormatter::with_word_diff`] does, and the words are merged
+    /// instead. The conflict is resolved if the two sides changed different words, and only
+    /// overlapping changes to the same words still conflict. Conflicts spanning several lines
+    /// are left as they are. This doesn't affect the provenance reported by
+    /// [`merge_with_provenance`](MergeOptions::merge_with_provenance), as a line merged this way
+    /// doesn't come from any of the files.
+    ///
+    /// [`PatchFormatter::with_word_diff`]: crate::PatchFormatter::with_word_diff
+    ///
+    /// ```
+    /// use diffy::MergeOptions;
+    ///
+    /// let original = "The quick brown fox jumps\n";
+    /// let ours = "The slow brown fox jumps\n";
+    /// let theirs = "The quick brown fox leaps\n";
+    ///
+    /// assert!(MergeOptions::new().merge(original, ours, theirs).is_err());
+    /// let merged = MergeOptions::new()
+    ///     .set_word_level(true)
+    ///     .merge(original, ours, theirs);
+    /// assert_eq!(merged.unwrap(), "The slow brown fox leaps\n");
+    /// ```
+    pub fn set_word_level(&mut self, word_level: bool) -> &mut Self {
+        self.word_level = word_level;
+        self
+    }
+
     /// Merge two files, given a common ancestor, based on the configured options
     pub fn merge<'a>(
         &self,
@@ -151,10 +265,11 @@ impl MergeOptions {
         ours: &'a str,
         theirs: &'a str,
     ) -> Result<String, String> {
+        let ending = self.line_ending.resolve(LineIter::new(ancestor));
         let mut classifier = Classifier::default();
-        let (ancestor_lines, ancestor_ids) = classifier.classify_lines(ancestor);
-        let (our_lines, our_ids) = classifier.classify_lines(ours);
-        let (their_lines, their_ids) = classifier.classify_lines(theirs);
+        let (ancestor_lines, ancestor_ids) = classify_lines(&mut classifier, ancestor, ending);
+        let (our_lines, our_ids) = classify_lines(&mut classifier, ours, ending);
+        let (their_lines, their_ids) = classify_lines(&mut classifier, theirs, ending);
 
         let opts = DiffOptions::default();
         let our_solution = opts.diff_slice(&ancestor_ids, &our_ids);
@@ -164,14 +279,17 @@ impl MergeOptions {
         let mut merge = diff3_range_to_merge_range(&merged);
 
         cleanup_conflicts(&mut merge);
+        if let ConflictStyle::ZealousDiff3 = self.style {
+            merge = minimize_conflicts(merge);
+        }
 
         output_result(
             &ancestor_lines,
             &our_lines,
             &their_lines,
             &merge,
-            self.conflict_marker_length,
-            self.style,
+            self,
+            ending,
         )
     }
 
@@ -182,10 +300,11 @@ impl MergeOptions {
         ours: &'a [u8],
         theirs: &'a [u8],
     ) -> Result<Vec<u8>, Vec<u8>> {
+        let ending = self.line_ending.resolve(LineIter::new(ancestor));
         let mut classifier = Classifier::default();
-        let (ancestor_lines, ancestor_ids) = classifier.classify_lines(ancestor);
-        let (our_lines, our_ids) = classifier.classify_lines(ours);
-        let (their_lines, their_ids) = classifier.classify_lines(theirs);
+        let (ancestor_lines, ancestor_ids) = classify_lines(&mut classifier, ancestor, ending);
+        let (our_lines, our_ids) = classify_lines(&mut classifier, ours, ending);
+        let (their_lines, their_ids) = classify_lines(&mut classifier, theirs, ending);
 
         let opts = DiffOptions::default();
         let our_solution = opts.diff_slice(&ancestor_ids, &our_ids);
@@ -195,16 +314,71 @@ impl MergeOptions {
         let mut merge = diff3_range_to_merge_range(&merged);
 
         cleanup_conflicts(&mut merge);
+        if let ConflictStyle::ZealousDiff3 = self.style {
+            merge = minimize_conflicts(merge);
+        }
 
         output_result_bytes(
             &ancestor_lines,
             &our_lines,
             &their_lines,
             &merge,
-            self.conflict_marker_length,
-            self.style,
+            self,
+            ending,
         )
     }
+
+    /// Merge two files, given a common ancestor, reporting where each line of the result came
+    /// from instead of rendering conflict markers
+    pub fn merge_with_provenance<'a>(
+        &self,
+        ancestor: &'a str,
+        ours: &'a str,
+        theirs: &'a str,
+    ) -> Vec<MergedLine<'a, str>> {
+        let ending = self.line_ending.resolve(LineIter::new(ancestor));
+        let mut classifier = Classifier::default();
+        let (ancestor_lines, ancestor_ids) = classify_lines(&mut classifier, ancestor, ending);
+        let (our_lines, our_ids) = classify_lines(&mut classifier, ours, ending);
+        let (their_lines, their_ids) = classify_lines(&mut classifier, theirs, ending);
+
+        let opts = DiffOptions::default();
+        let our_solution = opts.diff_slice(&ancestor_ids, &our_ids);
+        let their_solution = opts.diff_slice(&ancestor_ids, &their_ids);
+
+        let merged = merge_solutions(&our_solution, &their_solution);
+        let mut merge = diff3_range_to_merge_range(&merged);
+
+        cleanup_conflicts(&mut merge);
+
+        output_provenance(&ancestor_lines, &our_lines, &their_lines, &merge)
+    }
+
+    /// Perform a 3-way merge between potentially non-utf8 texts, reporting where each line of
+    /// the result came from
+    pub fn merge_bytes_with_provenance<'a>(
+        &self,
+        ancestor: &'a [u8],
+        ours: &'a [u8],
+        theirs: &'a [u8],
+    ) -> Vec<MergedLine<'a, [u8]>> {
+        let ending = self.line_ending.resolve(LineIter::new(ancestor));
+        let mut classifier = Classifier::default();
+        let (ancestor_lines, ancestor_ids) = classify_lines(&mut classifier, ancestor, ending);
+        let (our_lines, our_ids) = classify_lines(&mut classifier, ours, ending);
+        let (their_lines, their_ids) = classify_lines(&mut classifier, theirs, ending);
+
+        let opts = DiffOptions::default();
+        let our_solution = opts.diff_slice(&ancestor_ids, &our_ids);
+        let their_solution = opts.diff_slice(&ancestor_ids, &their_ids);
+
+        let merged = merge_solutions(&our_solution, &their_solution);
+        let mut merge = diff3_range_to_merge_range(&merged);
+
+        cleanup_conflicts(&mut merge);
+
+        output_provenance(&ancestor_lines, &our_lines, &their_lines, &merge)
+    }
 }
 
 impl Default for MergeOptions {
@@ -277,6 +451,35 @@ pub fn merge_bytes<'a>(
     MergeOptions::default().merge_bytes(ancestor, ours, theirs)
 }
 
+/// Merge two files given a common ancestor, reporting where each line of the result came from.
+///
+/// ```
+/// # use diffy::{merge_with_provenance, Origin};
+/// let original = "one\ntwo\nthree\n";
+/// let a = "1\ntwo\nthree\n";
+/// let b = "one\ntwo\n3\n";
+///
+/// let origins: Vec<_> = merge_with_provenance(original, a, b)
+///     .iter()
+///     .map(|line| (line.content(), line.origin()))
+///     .collect();
+/// assert_eq!(
+///     origins,
+///     [
+///         ("1\n", Origin::Ours),
+///         ("two\n", Origin::Ancestor),
+///         ("3\n", Origin::Theirs),
+///     ]
+/// );
+/// ```
+pub fn merge_with_provenance<'a>(
+    ancestor: &'a str,
+    ours: &'a str,
+    theirs: &'a str,
+) -> Vec<MergedLine<'a, str>> {
+    MergeOptions::default().merge_with_provenance(ancestor, ours, theirs)
+}
+
 fn merge_solutions<'ancestor, 'ours, 'theirs, T: ?Sized + SliceLike>(
     our_solution: &[DiffRange<'ancestor, 'ours, T>],
     their_solution: &[DiffRange<'ancestor, 'theirs, T>],
@@ -490,44 +693,215 @@ fn cleanup_conflicts<'ancestor, 'ours, 'theirs, T: ?Sized + SliceLike + PartialE
     }
 }
 
+// Moves lines which are the same on both sides at the edges of a conflict out of it
+fn minimize_conflicts<'ancestor, 'ours, 'theirs, T: ?Sized + SliceLike + PartialEq>(
+    solution: Vec<MergeRange<'ancestor, 'ours, 'theirs, T>>,
+) -> Vec<MergeRange<'ancestor, 'ours, 'theirs, T>> {
+    let mut minimized = Vec::with_capacity(solution.len());
+
+    for merge in solution {
+        let (ancestor, ours, theirs) = match merge {
+            MergeRange::Conflict(ancestor, ours, theirs) => (ancestor, ours, theirs),
+            _ => {
+                minimized.push(merge);
+                continue;
+            }
+        };
+
+        // The common prefix and suffix of both sides along with what's left of them in between
+        let split = || {
+            let prefix_len = ours.common_prefix_len(theirs);
+            let (our_prefix, ours) = ours.split_at_checked(prefix_len)?;
+            let (their_prefix, theirs) = theirs.split_at_checked(prefix_len)?;
+
+            let suffix_len = ours.common_suffix_len(theirs);
+            let (ours, our_suffix) = ours.split_at_checked(ours.len().checked_sub(suffix_len)?)?;
+            let (theirs, their_suffix) =
+                theirs.split_at_checked(theirs.len().checked_sub(suffix_len)?)?;
+            Some((
+                (our_prefix, their_prefix),
+                (ours, theirs),
+                (our_suffix, their_suffix),
+            ))
+        };
+        let ((our_prefix, their_prefix), (ours, theirs), (our_suffix, their_suffix)) = match split()
+        {
+            Some(split) => split,
+            None => {
+                minimized.push(MergeRange::Conflict(ancestor, ours, theirs));
+                continue;
+            }
+        };
+
+        if !our_prefix.is_empty() {
+            minimized.push(MergeRange::Both(our_prefix, their_prefix));
+        }
+        minimized.push(MergeRange::Conflict(ancestor, ours, theirs));
+        if !our_suffix.is_empty() {
+            minimized.push(MergeRange::Both(our_suffix, their_suffix));
+        }
+    }
+
+    minimized
+}
+
+// Classifies lines by their bytes, ignoring the difference between `\r\n` and `\n` if a line
+// ending is set
+fn classify_lines<'a, T: Text + ?Sized>(
+    classifier: &mut Classifier<'a, [u8]>,
+    text: &'a T,
+    ending: Option<LineEnding>,
+) -> (Vec<&'a T>, Vec<u64>) {
+    LineIter::new(text)
+        .map(|line| {
+            let key = match ending {
+                Some(_) => without_cr(line.as_bytes()),
+                None => Cow::Borrowed(line.as_bytes()),
+            };
+            (line, classifier.classify_key(key))
+        })
+        .unzip()
+}
+
+// Merges a conflict where each side is a single line word by word, returning the merged line if
+// the two sides changed different words
+fn merge_conflicting_line<T: Text + ?Sized>(
+    ancestor: &[&T],
+    ours: &[&T],
+    theirs: &[&T],
+    ending: Option<LineEnding>,
+) -> Option<Vec<u8>> {
+    let (ancestor, ours, theirs) = match (ancestor, ours, theirs) {
+        ([ancestor], [ours], [theirs]) => (ancestor.as_bytes(), ours.as_bytes(), theirs.as_bytes()),
+        _ => return None,
+    };
+    let (ancestor, ancestor_newline) = split_newline(ancestor, ending);
+    let (ours, our_newline) = split_newline(ours, ending);
+    let (theirs, their_newline) = split_newline(theirs, ending);
+
+    let mut classifier = Classifier::default();
+    let mut classify_words = |line| -> (Vec<&[u8]>, Vec<u64>) {
+        split_words(line)
+            .into_iter()
+            .map(|word| (word, classifier.classify_key(Cow::Borrowed(word))))
+            .unzip()
+    };
+    let (ancestor_words, ancestor_ids) = classify_words(ancestor);
+    let (our_words, our_ids) = classify_words(ours);
+    let (their_words, their_ids) = classify_words(theirs);
+
+    let opts = DiffOptions::default();
+    let our_solution = opts.diff_slice(&ancestor_ids, &our_ids);
+    let their_solution = opts.diff_slice(&ancestor_ids, &their_ids);
+
+    let merged = merge_solutions(&our_solution, &their_solution);
+    let mut merge = diff3_range_to_merge_range(&merged);
+    cleanup_conflicts(&mut merge);
+
+    let mut line = Vec::new();
+    for merge_range in merge {
+        let words = match merge_range {
+            MergeRange::Equal(range, ..) => &ancestor_words[range.range()],
+            MergeRange::Ours(range) | MergeRange::Both(range, _) => &our_words[range.range()],
+            MergeRange::Theirs(range) => &their_words[range.range()],
+            MergeRange::Conflict(..) => return None,
+        };
+        line.extend(words.iter().copied().flatten());
+    }
+
+    // Each side may also have changed the line's ending
+    let newline = if our_newline == ancestor_newline {
+        their_newline
+    } else if their_newline == ancestor_newline || their_newline == our_newline {
+        our_newline
+    } else {
+        return None;
+    };
+    line.extend_from_slice(newline);
+
+    Some(line)
+}
+
+// Splits off the line ending, which is only compared after the words. `\r\n` is kept as part of
+// the line ending unless the difference between `\r\n` and `\n` is ignored.
+fn split_newline(line: &[u8], ending: Option<LineEnding>) -> (&[u8], &[u8]) {
+    let newline = match ending {
+        Some(_) if line.ends_with(b"\r\n") => 2,
+        _ if line.ends_with(b"\n") => 1,
+        _ => 0,
+    };
+    line.split_at(line.len() - newline)
+}
+
 fn output_result<'a, T: ?Sized>(
     ancestor: &[&'a str],
     ours: &[&'a str],
     theirs: &[&'a str],
     merge: &[MergeRange<T>],
-    marker_len: usize,
-    style: ConflictStyle,
+    opts: &MergeOptions,
+    ending: Option<LineEnding>,
 ) -> Result<String, String> {
+    let marker_len = opts.conflict_marker_length;
+    let style = opts.style;
+    // Lines of the ancestor are only given the line ending if it was explicitly chosen
+    let base_ending = ending.filter(|_| opts.line_ending != LineEnding::Auto);
+    let our_ending = side_ending(ours, ending, opts);
+    let their_ending = side_ending(theirs, ending, opts);
     let mut conflicts = 0;
     let mut output = String::new();
+    let newline = ending.map_or("\n", LineEnding::as_str);
 
     for merge_range in merge {
         match merge_range {
-            MergeRange::Equal(range, ..) => {
-                output.extend(ancestor[range.range()].iter().copied());
+            MergeRange::Equal(range, our_range, their_range) => {
+                for (line, _) in equal_lines(
+                    &ancestor[range.range()],
+                    &ours[our_range.range()],
+                    &theirs[their_range.range()],
+                ) {
+                    push_lines(&mut output, &[line], base_ending);
+                }
             }
             MergeRange::Conflict(ancestor_range, ours_range, theirs_range) => {
-                add_conflict_marker(&mut output, '<', marker_len, Some("ours"));
-                output.extend(ours[ours_range.range()].iter().copied());
+                if let Some(line) = opts
+                    .word_level
+                    .then(|| {
+                        merge_conflicting_line(
+                            &ancestor[ancestor_range.range()],
+                            &ours[ours_range.range()],
+                            &theirs[theirs_range.range()],
+                            ending,
+                        )
+                    })
+                    .flatten()
+                {
+                    let line =
+                        String::from_utf8(line).expect("words are split at ascii whitespace");
+                    push_lines(&mut output, &[&line], ending);
+                    continue;
+                }
+
+                add_conflict_marker(&mut output, '<', marker_len, Some("ours"), newline);
+                push_lines(&mut output, &ours[ours_range.range()], our_ending);
 
-                if let ConflictStyle::Diff3 = style {
-                    add_conflict_marker(&mut output, '|', marker_len, Some("original"));
-                    output.extend(ancestor[ancestor_range.range()].iter().copied());
+                if let ConflictStyle::Diff3 | ConflictStyle::ZealousDiff3 = style {
+                    add_conflict_marker(&mut output, '|', marker_len, Some("original"), newline);
+                    push_lines(&mut output, &ancestor[ancestor_range.range()], base_ending);
                 }
 
-                add_conflict_marker(&mut output, '=', marker_len, None);
-                output.extend(theirs[theirs_range.range()].iter().copied());
-                add_conflict_marker(&mut output, '>', marker_len, Some("theirs"));
+                add_conflict_marker(&mut output, '=', marker_len, None, newline);
+                push_lines(&mut output, &theirs[theirs_range.range()], their_ending);
+                add_conflict_marker(&mut output, '>', marker_len, Some("theirs"), newline);
                 conflicts += 1;
             }
             MergeRange::Ours(range) => {
-                output.extend(ours[range.range()].iter().copied());
+                push_lines(&mut output, &ours[range.range()], our_ending);
             }
             MergeRange::Theirs(range) => {
-                output.extend(theirs[range.range()].iter().copied());
+                push_lines(&mut output, &theirs[range.range()], their_ending);
             }
             MergeRange::Both(range, _) => {
-                output.extend(ours[range.range()].iter().copied());
+                push_lines(&mut output, &ours[range.range()], our_ending);
             }
         }
     }
@@ -539,11 +913,100 @@ fn output_result<'a, T: ?Sized>(
     }
 }
 
+// Pushes lines, converting their line endings if needed
+fn push_lines(output: &mut String, lines: &[&str], ending: Option<LineEnding>) {
+    for line in lines {
+        match ending.map(|ending| ending.convert(*line)) {
+            Some((line, Some(ending))) => {
+                output.push_str(line);
+                output.push_str(ending);
+            }
+            _ => output.push_str(line),
+        }
+    }
+}
+
+// Returns the line ending to give the lines taken from a side. With `Auto`, a side which mostly
+// uses another line ending than the ancestor converted the file, so its lines are written as they
+// are.
+fn side_ending<T: Text + ?Sized>(
+    lines: &[&T],
+    ending: Option<LineEnding>,
+    opts: &MergeOptions,
+) -> Option<LineEnding> {
+    if opts.line_ending == LineEnding::Auto
+        && LineEnding::Auto.resolve(lines.iter().copied()) != ending
+    {
+        return None;
+    }
+    ending
+}
+
+// Lines compare equal regardless of their line ending when the ancestor mostly uses CRLF, so a
+// side which only changed the line ending of a line, e.g. by converting the whole file, still
+// changed it. Ours is preferred if both sides did.
+fn equal_lines<'a, 'b, T: PartialEq + ?Sized>(
+    ancestor: &'b [&'a T],
+    ours: &'b [&'a T],
+    theirs: &'b [&'a T],
+) -> impl Iterator<Item = (&'a T, Origin)> + 'b {
+    ancestor
+        .iter()
+        .zip(ours)
+        .zip(theirs)
+        .map(|((&ancestor, &ours), &theirs)| {
+            if ours != ancestor {
+                (ours, Origin::Ours)
+            } else if theirs != ancestor {
+                (theirs, Origin::Theirs)
+            } else {
+                (ancestor, Origin::Ancestor)
+            }
+        })
+}
+
+fn output_provenance<'a, T: PartialEq + ?Sized, U: ?Sized>(
+    ancestor: &[&'a T],
+    ours: &[&'a T],
+    theirs: &[&'a T],
+    merge: &[MergeRange<U>],
+) -> Vec<MergedLine<'a, T>> {
+    let mut output = Vec::new();
+    let mut push = |lines: &[&'a T], origin| {
+        output.extend(lines.iter().map(|&content| MergedLine { content, origin }));
+    };
+
+    for merge_range in merge {
+        match merge_range {
+            MergeRange::Equal(range, our_range, their_range) => {
+                for (line, origin) in equal_lines(
+                    &ancestor[range.range()],
+                    &ours[our_range.range()],
+                    &theirs[their_range.range()],
+                ) {
+                    push(&[line], origin);
+                }
+            }
+            MergeRange::Conflict(ancestor_range, ours_range, theirs_range) => {
+                push(&ours[ours_range.range()], Origin::ConflictOurs);
+                push(&ancestor[ancestor_range.range()], Origin::ConflictAncestor);
+                push(&theirs[theirs_range.range()], Origin::ConflictTheirs);
+            }
+            MergeRange::Ours(range) => push(&ours[range.range()], Origin::Ours),
+            MergeRange::Theirs(range) => push(&theirs[range.range()], Origin::Theirs),
+            MergeRange::Both(range, _) => push(&ours[range.range()], Origin::Both),
+        }
+    }
+
+    output
+}
+
 fn add_conflict_marker(
     output: &mut String,
     marker: char,
     marker_len: usize,
     filename: Option<&str>,
+    newline: &str,
 ) {
     for _ in 0..marker_len {
         output.push(marker);
@@ -553,7 +1016,7 @@ fn add_conflict_marker(
         output.push(' ');
         output.push_str(filename);
     }
-    output.push('\n');
+    output.push_str(newline);
 }
 
 fn output_result_bytes<'a, T: ?Sized>(
@@ -561,53 +1024,74 @@ fn output_result_bytes<'a, T: ?Sized>(
     ours: &[&'a [u8]],
     theirs: &[&'a [u8]],
     merge: &[MergeRange<T>],
-    marker_len: usize,
-    style: ConflictStyle,
+    opts: &MergeOptions,
+    ending: Option<LineEnding>,
 ) -> Result<Vec<u8>, Vec<u8>> {
+    let marker_len = opts.conflict_marker_length;
+    let style = opts.style;
+    // Lines of the ancestor are only given the line ending if it was explicitly chosen
+    let base_ending = ending.filter(|_| opts.line_ending != LineEnding::Auto);
+    let our_ending = side_ending(ours, ending, opts);
+    let their_ending = side_ending(theirs, ending, opts);
     let mut conflicts = 0;
     let mut output: Vec<u8> = Vec::new();
+    let newline = ending.map_or("\n", LineEnding::as_str).as_bytes();
 
     for merge_range in merge {
         match merge_range {
-            MergeRange::Equal(range, ..) => {
-                ancestor[range.range()]
-                    .iter()
-                    .for_each(|line| output.extend_from_slice(line));
+            MergeRange::Equal(range, our_range, their_range) => {
+                for (line, _) in equal_lines(
+                    &ancestor[range.range()],
+                    &ours[our_range.range()],
+                    &theirs[their_range.range()],
+                ) {
+                    push_lines_bytes(&mut output, &[line], base_ending);
+                }
             }
             MergeRange::Conflict(ancestor_range, ours_range, theirs_range) => {
-                add_conflict_marker_bytes(&mut output, b'<', marker_len, Some(b"ours"));
-                ours[ours_range.range()]
-                    .iter()
-                    .for_each(|line| output.extend_from_slice(line));
-
-                if let ConflictStyle::Diff3 = style {
-                    add_conflict_marker_bytes(&mut output, b'|', marker_len, Some(b"original"));
-                    ancestor[ancestor_range.range()]
-                        .iter()
-                        .for_each(|line| output.extend_from_slice(line));
+                if let Some(line) = opts
+                    .word_level
+                    .then(|| {
+                        merge_conflicting_line(
+                            &ancestor[ancestor_range.range()],
+                            &ours[ours_range.range()],
+                            &theirs[theirs_range.range()],
+                            ending,
+                        )
+                    })
+                    .flatten()
+                {
+                    push_lines_bytes(&mut output, &[&line], ending);
+                    continue;
                 }
 
-                add_conflict_marker_bytes(&mut output, b'=', marker_len, None);
-                theirs[theirs_range.range()]
-                    .iter()
-                    .for_each(|line| output.extend_from_slice(line));
-                add_conflict_marker_bytes(&mut output, b'>', marker_len, Some(b"theirs"));
+                add_conflict_marker_bytes(&mut output, b'<', marker_len, Some(b"ours"), newline);
+                push_lines_bytes(&mut output, &ours[ours_range.range()], our_ending);
+
+                if let ConflictStyle::Diff3 | ConflictStyle::ZealousDiff3 = style {
+                    add_conflict_marker_bytes(
+                        &mut output,
+                        b'|',
+                        marker_len,
+                        Some(b"original"),
+                        newline,
+                    );
+                    push_lines_bytes(&mut output, &ancestor[ancestor_range.range()], base_ending);
+                }
+
+                add_conflict_marker_bytes(&mut output, b'=', marker_len, None, newline);
+                push_lines_bytes(&mut output, &theirs[theirs_range.range()], their_ending);
+                add_conflict_marker_bytes(&mut output, b'>', marker_len, Some(b"theirs"), newline);
                 conflicts += 1;
             }
             MergeRange::Ours(range) => {
-                ours[range.range()]
-                    .iter()
-                    .for_each(|line| output.extend_from_slice(line));
+                push_lines_bytes(&mut output, &ours[range.range()], our_ending);
             }
             MergeRange::Theirs(range) => {
-                theirs[range.range()]
-                    .iter()
-                    .for_each(|line| output.extend_from_slice(line));
+                push_lines_bytes(&mut output, &theirs[range.range()], their_ending);
             }
             MergeRange::Both(range, _) => {
-                ours[range.range()]
-                    .iter()
-                    .for_each(|line| output.extend_from_slice(line));
+                push_lines_bytes(&mut output, &ours[range.range()], our_ending);
             }
         }
     }
@@ -619,11 +1103,24 @@ fn output_result_bytes<'a, T: ?Sized>(
     }
 }
 
+fn push_lines_bytes(output: &mut Vec<u8>, lines: &[&[u8]], ending: Option<LineEnding>) {
+    for line in lines {
+        match ending.map(|ending| ending.convert(*line)) {
+            Some((line, Some(ending))) => {
+                output.extend_from_slice(line);
+                output.extend_from_slice(ending.as_bytes());
+            }
+            _ => output.extend_from_slice(line),
+        }
+    }
+}
+
 fn add_conflict_marker_bytes(
     output: &mut Vec<u8>,
     marker: u8,
     marker_len: usize,
     filename: Option<&[u8]>,
+    newline: &[u8],
 ) {
     for _ in 0..marker_len {
         output.push(marker);
@@ -633,5 +1130,5 @@ fn add_conflict_marker_bytes(
         output.push(b' ');
         output.extend_from_slice(filename);
     }
-    output.push(b'\n');
+    output.extend_from_slice(newline);
 }
@@ -404,3 +404,333 @@ fn delete_and_insert_conflict() {
         "MergeRange (Theirs::delete, Ours::insert) conflict"
     );
 }
+
+#[test]
+fn provenance() {
+    let base = "\
+alpha
+beta
+gamma
+delta
+epsilon
+";
+    let ours = "\
+alpha
+BETA
+gamma
+delta
+epsilon
+zeta
+";
+    let theirs = "\
+alpha
+beta
+gamma
+DELTA
+epsilon
+zeta
+";
+
+    let lines = merge_with_provenance(base, ours, theirs);
+    let origins: Vec<_> = lines
+        .iter()
+        .map(|line| (line.content(), line.origin()))
+        .collect();
+    assert_eq!(
+        origins,
+        [
+            ("alpha\n", Origin::Ancestor),
+            ("BETA\n", Origin::Ours),
+            ("gamma\n", Origin::Ancestor),
+            ("DELTA\n", Origin::Theirs),
+            ("epsilon\n", Origin::Ancestor),
+            ("zeta\n", Origin::Both),
+        ]
+    );
+    let merged: String = lines.iter().map(|line| line.content()).collect();
+    assert_eq!(Ok(merged), merge(base, ours, theirs));
+
+    let lines = MergeOptions::new().merge_bytes_with_provenance(
+        base.as_bytes(),
+        ours.as_bytes(),
+        theirs.as_bytes(),
+    );
+    let origins: Vec<_> = lines.iter().map(|line| line.origin()).collect();
+    assert_eq!(
+        origins,
+        [
+            Origin::Ancestor,
+            Origin::Ours,
+            Origin::Ancestor,
+            Origin::Theirs,
+            Origin::Ancestor,
+            Origin::Both,
+        ]
+    );
+
+    let ours = "alpha\nours\n";
+    let theirs = "alpha\ntheirs\n";
+    let origins: Vec<_> = merge_with_provenance("alpha\nbase\n", ours, theirs)
+        .iter()
+        .map(|line| (line.content(), line.origin()))
+        .collect();
+    assert_eq!(
+        origins,
+        [
+            ("alpha\n", Origin::Ancestor),
+            ("ours\n", Origin::ConflictOurs),
+            ("base\n", Origin::ConflictAncestor),
+            ("theirs\n", Origin::ConflictTheirs),
+        ]
+    );
+}
+
+#[test]
+fn zealous_diff3() {
+    let base = "\
+a
+X
+b
+";
+    let ours = "\
+a
+same1
+ours
+same2
+b
+";
+    let theirs = "\
+a
+same1
+theirs
+same2
+b
+";
+
+    let expected = "\
+a
+<<<<<<< ours
+same1
+ours
+same2
+||||||| original
+X
+=======
+same1
+theirs
+same2
+>>>>>>> theirs
+b
+";
+    assert_merge!(
+        base,
+        ours,
+        theirs,
+        Err(expected),
+        "Diff3 keeps the whole conflict"
+    );
+
+    let expected = "\
+a
+same1
+<<<<<<< ours
+ours
+||||||| original
+X
+=======
+theirs
+>>>>>>> theirs
+same2
+b
+";
+    let mut opts = MergeOptions::new();
+    opts.set_conflict_style(ConflictStyle::ZealousDiff3);
+    assert_eq!(opts.merge(base, ours, theirs).unwrap_err(), expected);
+    assert_eq!(
+        opts.merge_bytes(base.as_bytes(), ours.as_bytes(), theirs.as_bytes())
+            .unwrap_err(),
+        expected.as_bytes()
+    );
+
+    // Without common lines at the edges the conflict is left as is
+    let theirs = "\
+a
+theirs
+b
+";
+    let expected = "\
+a
+<<<<<<< ours
+same1
+ours
+same2
+||||||| original
+X
+=======
+theirs
+>>>>>>> theirs
+b
+";
+    assert_eq!(opts.merge(base, ours, theirs).unwrap_err(), expected);
+}
+
+#[test]
+fn line_endings() {
+    let base = "a\r\nb\r\nc\r\nd\r\ne\r\n";
+    // Rewritten with LF line endings by an editor
+    let ours = "A\nb\nc\nd\ne\n";
+    let theirs = "a\r\nb\r\nc\r\nd\r\nE\r\n";
+
+    // Lines are matched regardless of their line ending, but the conversion of ours is kept
+    let mut opts = MergeOptions::new();
+    assert_eq!(opts.merge(base, ours, theirs).unwrap(), "A\nb\nc\nd\nE\r\n");
+
+    opts.set_line_ending(LineEnding::CrLf);
+    assert_eq!(
+        opts.merge(base, ours, theirs).unwrap(),
+        "A\r\nb\r\nc\r\nd\r\nE\r\n"
+    );
+
+    opts.set_line_ending(LineEnding::Lf);
+    assert_eq!(opts.merge(base, ours, theirs).unwrap(), "A\nb\nc\nd\nE\n");
+    assert_eq!(
+        opts.merge_bytes(base.as_bytes(), ours.as_bytes(), theirs.as_bytes())
+            .unwrap(),
+        b"A\nb\nc\nd\nE\n"
+    );
+
+    // Conflict markers use the line ending too
+    let theirs = "a2\r\nb\r\nc\r\nd\r\ne\r\n";
+    opts.set_line_ending(LineEnding::Auto);
+    let expected = "\
+<<<<<<< ours\r
+A
+||||||| original\r
+a\r
+=======\r
+a2\r
+>>>>>>> theirs\r
+b
+c
+d
+e
+";
+    assert_eq!(opts.merge(base, ours, theirs).unwrap_err(), expected);
+    assert_eq!(
+        opts.merge_bytes(base.as_bytes(), ours.as_bytes(), theirs.as_bytes())
+            .unwrap_err(),
+        expected.as_bytes()
+    );
+
+    // An LF ancestor is compared exactly by default, so every line of ours changed
+    let base = "a\nb\nc\n";
+    let ours = "a\r\nb\r\nc\r\n";
+    let theirs = "a\nB\nc\n";
+    assert!(merge(base, ours, theirs).is_err());
+    opts.set_line_ending(LineEnding::Lf);
+    assert_eq!(opts.merge(base, ours, theirs).unwrap(), "a\nB\nc\n");
+}
+
+#[test]
+fn one_side_converts_line_endings() {
+    let base = "a\r\nb\r\nc\r\n";
+    let converted = "a\nb\nc\n";
+
+    assert_eq!(merge(base, converted, base).unwrap(), converted);
+    assert_eq!(merge(base, base, converted).unwrap(), converted);
+    assert_eq!(
+        merge_bytes(base.as_bytes(), converted.as_bytes(), base.as_bytes()).unwrap(),
+        converted.as_bytes()
+    );
+
+    // The other side's changes are still merged, with their own line endings
+    assert_eq!(
+        merge(base, converted, "a\r\nB\r\nc\r\n").unwrap(),
+        "a\nB\r\nc\n"
+    );
+    // And a line inserted by a side which kept the line endings gets them
+    assert_eq!(
+        merge(base, converted, "a\r\nb\r\nb2\nc\r\n").unwrap(),
+        "a\nb\nb2\r\nc\n"
+    );
+
+    let origins: Vec<_> = MergeOptions::new()
+        .merge_with_provenance(base, converted, base)
+        .iter()
+        .map(|line| (line.content(), line.origin()))
+        .collect();
+    assert_eq!(
+        origins,
+        [
+            ("a\n", Origin::Ours),
+            ("b\n", Origin::Ours),
+            ("c\n", Origin::Ours)
+        ]
+    );
+}
+
+#[test]
+fn word_level() {
+    let base = "\
+fn main() {
+    let greeting = format!(\"Hello, {}!\", name);
+    println!(\"{}\", greeting);
+}
+";
+    let ours = "\
+fn main() {
+    let message = format!(\"Hello, {}!\", name);
+    println!(\"{}\", greeting);
+}
+";
+    let theirs = "\
+fn main() {
+    let greeting = format!(\"Hello, {}!\", user.name);
+    println!(\"{}\", greeting);
+}
+";
+
+    let mut opts = MergeOptions::new();
+    assert!(opts.merge(base, ours, theirs).is_err());
+
+    opts.set_word_level(true);
+    let expected = "\
+fn main() {
+    let message = format!(\"Hello, {}!\", user.name);
+    println!(\"{}\", greeting);
+}
+";
+    assert_eq!(opts.merge(base, ours, theirs).unwrap(), expected);
+    assert_eq!(
+        opts.merge_bytes(base.as_bytes(), ours.as_bytes(), theirs.as_bytes())
+            .unwrap(),
+        expected.as_bytes()
+    );
+
+    // Changing the same word differently still conflicts
+    let theirs = theirs.replace("greeting =", "salutation =");
+    let expected = "\
+fn main() {
+<<<<<<< ours
+    let message = format!(\"Hello, {}!\", name);
+||||||| original
+    let greeting = format!(\"Hello, {}!\", name);
+=======
+    let salutation = format!(\"Hello, {}!\", user.name);
+>>>>>>> theirs
+    println!(\"{}\", greeting);
+}
+";
+    assert_eq!(opts.merge(base, ours, &theirs).unwrap_err(), expected);
+
+    // As do conflicts spanning several lines
+    let ours = "a\nb c\nd\n";
+    let theirs = "a\nb C\nD\n";
+    assert!(opts.merge("a\nb\nd\n", ours, theirs).is_err());
+
+    // The line ending is merged too
+    let base = "one two\n";
+    let ours = "one 2\r\n";
+    let theirs = "1 two\n";
+    assert_eq!(opts.merge(base, ours, theirs).unwrap(), "1 2\r\n");
+}
@@ -1,7 +1,9 @@
-use super::{Hunk, Line, Patch, NO_NEWLINE_AT_EOF};
+use super::{Hunk, Line, LineKind, Patch, NO_NEWLINE_AT_EOF};
+use crate::{diff::DiffOptions, range::DiffRange, utils::split_words};
 use nu_ansi_term::{Color, Style};
 use std::{
-    fmt::{Display, Formatter, Result},
+    cmp,
+    fmt::{self, Display, Formatter, Result},
     io,
 };
 
@@ -9,6 +11,14 @@ use std::{
 #[derive(Debug)]
 pub struct PatchFormatter {
     with_color: bool,
+    word_diff: bool,
+    inline_changes: bool,
+    lines_only: Option<LineKind>,
+    wrap_at: Option<usize>,
+
+    context_prefix: char,
+    delete_prefix: char,
+    insert_prefix: char,
 
     context: Style,
     delete: Style,
@@ -23,6 +33,14 @@ impl PatchFormatter {
     pub fn new() -> Self {
         Self {
             with_color: false,
+            word_diff: false,
+            inline_changes: false,
+            lines_only: None,
+            wrap_at: None,
+
+            context_prefix: ' ',
+            delete_prefix: '-',
+            insert_prefix: '+',
 
             context: Style::new(),
             delete: Color::Red.normal(),
@@ -39,11 +57,79 @@ impl PatchFormatter {
         self
     }
 
+    /// Render changed lines as an inline word diff, similar to `git diff --word-diff`
+    ///
+    /// Each deleted line which is directly followed by an inserted line is paired with it and
+    /// the two are emitted as a single line with the removed words marked as `[-old-]` and the
+    /// added words marked as `{+new+}`. Lines which can't be paired are emitted as usual, and so
+    /// are pairs too long to compare by words, see
+    /// [`DiffOptions::set_max_char_diff_bytes`](crate::DiffOptions::set_max_char_diff_bytes).
+    ///
+    /// Patches formatted this way can't be parsed back into a `Patch`.
+    pub fn with_word_diff(mut self) -> Self {
+        self.word_diff = true;
+        self
+    }
+
+    /// Render changed lines as their common prefix and suffix with the part in between shown as
+    /// `{old → new}`
+    ///
+    /// Lines are paired up like with [`with_word_diff`](PatchFormatter::with_word_diff), which
+    /// takes precedence if both are enabled. This is the densest format for lines changed in a
+    /// single place, e.g. ` let x = {foo → bar}(1);`.
+    ///
+    /// Patches formatted this way can't be parsed back into a `Patch`.
+    pub fn with_inline_changes(mut self) -> Self {
+        self.inline_changes = true;
+        self
+    }
+
+    /// Only emit the content of lines of the given kind
+    ///
+    /// The selected lines from all hunks are concatenated as is, without any headers, prefixes
+    /// or color. For example `LineKind::Insert` produces all of the text added by the patch.
+    pub fn with_lines_only(mut self, kind: LineKind) -> Self {
+        self.lines_only = Some(kind);
+        self
+    }
+
+    /// Wrap lines so that no row of output is wider than `width` characters
+    ///
+    /// The part of a line which doesn't fit is continued on the next row, prefixed again with the
+    /// line's prefix followed by `↳`. Widths are counted in chars, so wide or combining chars
+    /// may still make a row overflow. Non-utf8 lines, headers and paired up changed lines aren't
+    /// wrapped.
+    ///
+    /// Patches formatted this way can't be parsed back into a `Patch`.
+    pub fn with_wrap_at(mut self, width: usize) -> Self {
+        self.wrap_at = Some(width);
+        self
+    }
+
+    /// Set the character used to prefix context lines, defaults to `' '`
+    pub fn with_context_prefix(mut self, prefix: char) -> Self {
+        self.context_prefix = prefix;
+        self
+    }
+
+    /// Set the character used to prefix deleted lines, defaults to `'-'`
+    pub fn with_delete_prefix(mut self, prefix: char) -> Self {
+        self.delete_prefix = prefix;
+        self
+    }
+
+    /// Set the character used to prefix inserted lines, defaults to `'+'`
+    pub fn with_insert_prefix(mut self, prefix: char) -> Self {
+        self.insert_prefix = prefix;
+        self
+    }
+
     /// Returns a `Display` impl which can be used to print a Patch
     pub fn fmt_patch<'a>(&'a self, patch: &'a Patch<'a, str>) -> impl Display + 'a {
         PatchDisplay { f: self, patch }
     }
 
+    /// Write a Patch into a writer `W: io::Write`
     pub fn write_patch_into<T: ToOwned + AsRef<[u8]> + ?Sized, W: io::Write>(
         &self,
         patch: &Patch<'_, T>,
@@ -75,6 +161,200 @@ impl PatchFormatter {
     ) -> io::Result<()> {
         LineDisplay { f: self, line }.write_into(w)
     }
+
+    fn write_lines_into<T: AsRef<[u8]> + ?Sized, W: io::Write>(
+        &self,
+        lines: &[Line<'_, T>],
+        mut w: W,
+    ) -> io::Result<()> {
+        let mut i = 0;
+        while i < lines.len() {
+            let (deletes, inserts) = if self.word_diff || self.inline_changes {
+                count_replaced_lines(&lines[i..])
+            } else {
+                (0, 0)
+            };
+
+            if deletes == 0 || inserts == 0 {
+                self.write_line_into(&lines[i], &mut w)?;
+                i += 1;
+                continue;
+            }
+
+            let pairs = cmp::min(deletes, inserts);
+            for n in 0..pairs {
+                if let (Line::Delete(old), Line::Insert(new)) =
+                    (&lines[i + n], &lines[i + deletes + n])
+                {
+                    let (old, new) = (old.as_ref(), new.as_ref());
+                    if self.word_diff
+                        && !DiffOptions::default().diffs_by_chars(old.len() + new.len())
+                    {
+                        self.write_line_into(&lines[i + n], &mut w)?;
+                        self.write_line_into(&lines[i + deletes + n], &mut w)?;
+                    } else if self.word_diff {
+                        self.write_word_diff_into(old, new, &mut w)?;
+                    } else {
+                        self.write_inline_change_into(old, new, &mut w)?;
+                    }
+                }
+            }
+            for line in lines[i + pairs..i + deletes]
+                .iter()
+                .chain(&lines[i + deletes + pairs..i + deletes + inserts])
+            {
+                self.write_line_into(line, &mut w)?;
+            }
+            i += deletes + inserts;
+        }
+
+        Ok(())
+    }
+
+    fn write_word_diff_into<W: io::Write>(
+        &self,
+        old: &[u8],
+        new: &[u8],
+        mut w: W,
+    ) -> io::Result<()> {
+        let old_words = split_words(old.strip_suffix(b"\n").unwrap_or(old));
+        let new_words = split_words(new.strip_suffix(b"\n").unwrap_or(new));
+
+        write!(w, "{}", self.context_prefix)?;
+        for diff in DiffOptions::default().diff_slice(&old_words, &new_words) {
+            let (words, style, open, close) = match diff {
+                DiffRange::Equal(range, _) => {
+                    for word in range.as_slice() {
+                        w.write_all(word)?;
+                    }
+                    continue;
+                }
+                DiffRange::Delete(range) => (range.as_slice(), self.delete, "[-", "-]"),
+                DiffRange::Insert(range) => (range.as_slice(), self.insert, "{+", "+}"),
+            };
+
+            if self.with_color {
+                write!(w, "{}", style.prefix())?;
+            }
+            write!(w, "{}", open)?;
+            for word in words {
+                w.write_all(word)?;
+            }
+            write!(w, "{}", close)?;
+            if self.with_color {
+                write!(w, "{}", style.suffix())?;
+            }
+        }
+        writeln!(w)?;
+
+        if !new.ends_with(b"\n") {
+            writeln!(w, "{}", NO_NEWLINE_AT_EOF)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_inline_change_into<W: io::Write>(
+        &self,
+        old: &[u8],
+        new: &[u8],
+        mut w: W,
+    ) -> io::Result<()> {
+        let old = old.strip_suffix(b"\n").unwrap_or(old);
+        let new_content = new.strip_suffix(b"\n").unwrap_or(new);
+        let (prefix_len, suffix_len) = common_affix_lens(old, new_content);
+
+        let write_styled = |w: &mut W, content: &[u8], style: Style| -> io::Result<()> {
+            if self.with_color {
+                write!(w, "{}", style.prefix())?;
+            }
+            w.write_all(&content[prefix_len..content.len() - suffix_len])?;
+            if self.with_color {
+                write!(w, "{}", style.suffix())?;
+            }
+            Ok(())
+        };
+
+        write!(w, "{}", self.context_prefix)?;
+        w.write_all(&old[..prefix_len])?;
+        write!(w, "{{")?;
+        write_styled(&mut w, old, self.delete)?;
+        write!(w, " → ")?;
+        write_styled(&mut w, new_content, self.insert)?;
+        write!(w, "}}")?;
+        w.write_all(&old[old.len() - suffix_len..])?;
+        writeln!(w)?;
+
+        if !new.ends_with(b"\n") {
+            writeln!(w, "{}", NO_NEWLINE_AT_EOF)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Returns the lengths of the longest common prefix and suffix of `a` and `b` which don't overlap
+// and, for utf8 text, don't split a char
+fn common_affix_lens(a: &[u8], b: &[u8]) -> (usize, usize) {
+    let is_char_boundary =
+        |text: &[u8], i: usize| text.get(i).map_or(true, |&byte| (byte as i8) >= -0x40);
+
+    let mut prefix_len = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    while !is_char_boundary(a, prefix_len) || !is_char_boundary(b, prefix_len) {
+        prefix_len -= 1;
+    }
+
+    let max_suffix_len = cmp::min(a.len(), b.len()) - prefix_len;
+    let mut suffix_len = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take(max_suffix_len)
+        .take_while(|(x, y)| x == y)
+        .count();
+    while !is_char_boundary(a, a.len() - suffix_len) || !is_char_boundary(b, b.len() - suffix_len) {
+        suffix_len -= 1;
+    }
+
+    (prefix_len, suffix_len)
+}
+
+// Returns the number of deleted lines at the start of `lines` and the number of inserted lines
+// directly following them
+fn count_replaced_lines<T: ?Sized>(lines: &[Line<'_, T>]) -> (usize, usize) {
+    let deletes = lines
+        .iter()
+        .take_while(|line| matches!(line, Line::Delete(_)))
+        .count();
+    let inserts = lines[deletes..]
+        .iter()
+        .take_while(|line| matches!(line, Line::Insert(_)))
+        .count();
+    (deletes, inserts)
+}
+
+const WRAP_MARKER: char = '↳';
+
+// Splits `line` into the segments written on each row when wrapping at `width`, which includes
+// the prefix of every row and the marker of continuation rows. The trailing newline stays part
+// of the last segment.
+fn wrap_line(line: &str, width: usize) -> Vec<&str> {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut room = cmp::max(width.saturating_sub(1), 1);
+    let mut count = 0;
+    for (i, _) in content.char_indices() {
+        if count == room {
+            segments.push(&line[start..i]);
+            start = i;
+            count = 0;
+            room = cmp::max(width.saturating_sub(2), 1);
+        }
+        count += 1;
+    }
+    segments.push(&line[start..]);
+    segments
 }
 
 impl Default for PatchFormatter {
@@ -88,12 +368,36 @@ struct PatchDisplay<'a, T: ToOwned + ?Sized> {
     patch: &'a Patch<'a, T>,
 }
 
+impl<'a, T: ToOwned + ?Sized> PatchDisplay<'a, T> {
+    fn selected_lines(&self, kind: LineKind) -> impl Iterator<Item = &'a T> + 'a {
+        self.patch
+            .hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(move |line| line.kind() == kind)
+            .map(Line::content)
+    }
+}
+
 impl<T: ToOwned + AsRef<[u8]> + ?Sized> PatchDisplay<'_, T> {
     fn write_into<W: io::Write>(&self, mut w: W) -> io::Result<()> {
-        if self.patch.original.is_some() || self.patch.modified.is_some() {
+        if let Some(kind) = self.f.lines_only {
+            for line in self.selected_lines(kind) {
+                w.write_all(line.as_ref())?;
+            }
+            return Ok(());
+        }
+
+        if self.patch.index.is_some()
+            || self.patch.original.is_some()
+            || self.patch.modified.is_some()
+        {
             if self.f.with_color {
                 write!(w, "{}", self.f.patch_header.prefix())?;
             }
+            if let Some(index) = &self.patch.index {
+                writeln!(w, "{}", index)?;
+            }
             if let Some(original) = &self.patch.original {
                 write!(w, "--- ")?;
                 original.write_into(&mut w)?;
@@ -113,16 +417,32 @@ impl<T: ToOwned + AsRef<[u8]> + ?Sized> PatchDisplay<'_, T> {
             self.f.write_hunk_into(hunk, &mut w)?;
         }
 
+        for line in self.patch.binary.iter().flatten() {
+            w.write_all(line.as_ref())?;
+        }
+
         Ok(())
     }
 }
 
 impl Display for PatchDisplay<'_, str> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        if self.patch.original.is_some() || self.patch.modified.is_some() {
+        if let Some(kind) = self.f.lines_only {
+            return self
+                .selected_lines(kind)
+                .try_for_each(|line| f.write_str(line));
+        }
+
+        if self.patch.index.is_some()
+            || self.patch.original.is_some()
+            || self.patch.modified.is_some()
+        {
             if self.f.with_color {
                 write!(f, "{}", self.f.patch_header.prefix())?;
             }
+            if let Some(index) = &self.patch.index {
+                writeln!(f, "{}", index)?;
+            }
             if let Some(original) = &self.patch.original {
                 writeln!(f, "--- {}", original)?;
             }
@@ -138,6 +458,10 @@ impl Display for PatchDisplay<'_, str> {
             write!(f, "{}", self.f.fmt_hunk(hunk))?;
         }
 
+        for line in self.patch.binary.iter().flatten() {
+            f.write_str(line)?;
+        }
+
         Ok(())
     }
 }
@@ -162,7 +486,6 @@ impl<T: AsRef<[u8]> + ?Sized> HunkDisplay<'_, T> {
             if self.f.with_color {
                 write!(w, "{}", self.f.function_context.prefix())?;
             }
-            write!(w, " ")?;
             w.write_all(ctx.as_ref())?;
             if self.f.with_color {
                 write!(w, "{}", self.f.function_context.suffix())?;
@@ -170,11 +493,7 @@ impl<T: AsRef<[u8]> + ?Sized> HunkDisplay<'_, T> {
         }
         writeln!(w)?;
 
-        for line in &self.hunk.lines {
-            self.f.write_line_into(line, &mut w)?;
-        }
-
-        Ok(())
+        self.f.write_lines_into(&self.hunk.lines, w)
     }
 }
 
@@ -193,13 +512,23 @@ impl Display for HunkDisplay<'_, str> {
             if self.f.with_color {
                 write!(f, "{}", self.f.function_context.prefix())?;
             }
-            write!(f, " {}", ctx)?;
+            write!(f, "{}", ctx)?;
             if self.f.with_color {
                 write!(f, "{}", self.f.function_context.suffix())?;
             }
         }
         writeln!(f)?;
 
+        if self.f.word_diff || self.f.inline_changes {
+            // Lines are only split on ascii whitespace or char boundaries so the output remains
+            // valid utf8
+            let mut buf = Vec::new();
+            self.f
+                .write_lines_into(&self.hunk.lines, &mut buf)
+                .map_err(|_| fmt::Error)?;
+            return f.write_str(std::str::from_utf8(&buf).map_err(|_| fmt::Error)?);
+        }
+
         for line in &self.hunk.lines {
             write!(f, "{}", self.f.fmt_line(line))?;
         }
@@ -216,17 +545,32 @@ struct LineDisplay<'a, T: ?Sized> {
 impl<T: AsRef<[u8]> + ?Sized> LineDisplay<'_, T> {
     fn write_into<W: io::Write>(&self, mut w: W) -> io::Result<()> {
         let (sign, line, style) = match self.line {
-            Line::Context(line) => (' ', line.as_ref(), self.f.context),
-            Line::Delete(line) => ('-', line.as_ref(), self.f.delete),
-            Line::Insert(line) => ('+', line.as_ref(), self.f.insert),
+            Line::Context(line) => (self.f.context_prefix, line.as_ref(), self.f.context),
+            Line::Delete(line) => (self.f.delete_prefix, line.as_ref(), self.f.delete),
+            Line::Insert(line) => (self.f.insert_prefix, line.as_ref(), self.f.insert),
         };
 
         if self.f.with_color {
             write!(w, "{}", style.prefix())?;
         }
 
+        let wrapped = match (self.f.wrap_at, std::str::from_utf8(line)) {
+            (Some(width), Ok(line)) => Some(wrap_line(line, width)),
+            _ => None,
+        };
+
         if sign == ' ' && line == b"\n" {
             w.write_all(line)?;
+        } else if let Some(segments) = wrapped {
+            for (i, segment) in segments.into_iter().enumerate() {
+                if i > 0 {
+                    writeln!(w)?;
+                    write!(w, "{}{}", sign, WRAP_MARKER)?;
+                } else {
+                    write!(w, "{}", sign)?;
+                }
+                w.write_all(segment.as_bytes())?;
+            }
         } else {
             write!(w, "{}", sign)?;
             w.write_all(line)?;
@@ -248,9 +592,9 @@ impl<T: AsRef<[u8]> + ?Sized> LineDisplay<'_, T> {
 impl Display for LineDisplay<'_, str> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         let (sign, line, style) = match self.line {
-            Line::Context(line) => (' ', line, self.f.context),
-            Line::Delete(line) => ('-', line, self.f.delete),
-            Line::Insert(line) => ('+', line, self.f.insert),
+            Line::Context(line) => (self.f.context_prefix, line, self.f.context),
+            Line::Delete(line) => (self.f.delete_prefix, line, self.f.delete),
+            Line::Insert(line) => (self.f.insert_prefix, line, self.f.insert),
         };
 
         if self.f.with_color {
@@ -259,6 +603,16 @@ impl Display for LineDisplay<'_, str> {
 
         if sign == ' ' && *line == "\n" {
             write!(f, "{}", line)?;
+        } else if let Some(width) = self.f.wrap_at {
+            for (i, segment) in wrap_line(line, width).into_iter().enumerate() {
+                if i > 0 {
+                    writeln!(f)?;
+                    write!(f, "{}{}", sign, WRAP_MARKER)?;
+                } else {
+                    write!(f, "{}", sign)?;
+                }
+                write!(f, "{}", segment)?;
+            }
         } else {
             write!(f, "{}{}", sign, line)?;
         }
@@ -1,10 +1,16 @@
 mod format;
+mod normal;
+mod owned;
 mod parse;
+mod set;
 
 pub use format::PatchFormatter;
+pub use owned::OwnedPatch;
 pub use parse::ParsePatchError;
+pub use set::PatchSet;
 
-use std::{borrow::Cow, fmt, ops};
+use crate::utils::{LineIter, Text};
+use std::{borrow::Cow, cmp, fmt, io, ops};
 
 const NO_NEWLINE_AT_EOF: &str = "\\ No newline at end of file";
 
@@ -17,6 +23,10 @@ pub struct Patch<'a, T: ToOwned + ?Sized> {
     original: Option<Filename<'a, T>>,
     modified: Option<Filename<'a, T>>,
     hunks: Vec<Hunk<'a, T>>,
+    // The `index` line of a git patch, boxed to keep patches small since errors carry them
+    index: Option<Box<IndexLine>>,
+    // The lines of a `GIT binary patch` section, kept verbatim
+    binary: Option<Vec<&'a T>>,
 }
 
 impl<'a, T: ToOwned + ?Sized> Patch<'a, T> {
@@ -35,6 +45,8 @@ impl<'a, T: ToOwned + ?Sized> Patch<'a, T> {
             original,
             modified,
             hunks,
+            index: None,
+            binary: None,
         }
     }
 
@@ -49,16 +61,259 @@ impl<'a, T: ToOwned + ?Sized> Patch<'a, T> {
     }
 
     /// Returns the hunks in the patch
-    pub fn hunks(&self) -> &[Hunk<'_, T>] {
+    pub fn hunks(&self) -> &[Hunk<'a, T>] {
         &self.hunks
     }
 
+    /// Returns the lines of the `GIT binary patch` section of a parsed patch, starting with the
+    /// `GIT binary patch` line itself
+    ///
+    /// The section is kept verbatim so that it's written back out when formatting the patch, but
+    /// its content isn't decoded and applying the patch leaves binary files untouched.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let s = "\
+    /// diff --git a/logo.png b/logo.png
+    /// index e69de29..9a4bd1b 100644
+    /// GIT binary patch
+    /// literal 4
+    /// LcmZ?wbhHKl0|5X6
+    ///
+    /// literal 0
+    /// HcmV?d00001
+    ///
+    /// ";
+    ///
+    /// let patch = Patch::from_str(s).unwrap();
+    /// assert!(patch.hunks().is_empty());
+    /// assert_eq!(patch.binary().unwrap()[0], "GIT binary patch\n");
+    /// assert!(s.ends_with(&patch.to_string()));
+    /// ```
+    pub fn binary(&self) -> Option<&[&'a T]> {
+        self.binary.as_deref()
+    }
+
+    /// Returns the `index` line of a parsed git patch, which is written back out before the
+    /// filename headers when formatting the patch
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let s = "\
+    /// diff --git a/file.txt b/file.txt
+    /// index 257cc56..3bd1f0e 100644
+    /// --- a/file.txt
+    /// +++ b/file.txt
+    /// @@ -1 +1 @@
+    /// -foo
+    /// +bar
+    /// ";
+    ///
+    /// let patch = Patch::from_str(s).unwrap();
+    /// let index = patch.index().unwrap();
+    /// assert_eq!(index.old_id(), "257cc56");
+    /// assert_eq!(index.new_id(), "3bd1f0e");
+    /// assert_eq!(index.mode(), Some("100644"));
+    /// assert!(patch.to_string().starts_with("index 257cc56..3bd1f0e 100644\n--- a/file.txt\n"));
+    /// ```
+    pub fn index(&self) -> Option<&IndexLine> {
+        self.index.as_deref()
+    }
+
+    /// Returns a mutable reference to the hunks in the patch
+    ///
+    /// After modifying the hunks, [`renumber`] can be used to fix up the hunk
+    /// ranges and [`validate`] to check that the patch is still well formed.
+    ///
+    /// [`renumber`]: #method.renumber
+    /// [`validate`]: #method.validate
+    pub fn hunks_mut(&mut self) -> &mut Vec<Hunk<'a, T>> {
+        &mut self.hunks
+    }
+
+    /// Recalculate the ranges of each hunk based on its lines
+    ///
+    /// The starting line of each hunk's old range is left untouched while the
+    /// lengths of both ranges are recounted and the start of each hunk's new
+    /// range is shifted by the lines added or removed by the preceding hunks.
+    pub fn renumber(&mut self) {
+        let mut offset = 0isize;
+        for hunk in &mut self.hunks {
+            let (old_len, new_len) = hunk_lines_count(&hunk.lines);
+            let position = hunk.old_range.position();
+
+            hunk.old_range = HunkRange::from_position(position, old_len);
+            hunk.new_range =
+                HunkRange::from_position((position as isize + offset) as usize, new_len);
+
+            offset += new_len as isize - old_len as isize;
+        }
+    }
+
+    /// Sort the hunks by the starting line of their old range
+    pub fn sort_hunks(&mut self) {
+        self.hunks.sort_by_key(|hunk| hunk.old_range.start);
+    }
+
+    /// Sort the hunks and merge any whose old ranges overlap, e.g. after concatenating the
+    /// hunks of two patches
+    ///
+    /// Overlapping hunks can only be merged if the overlapping lines are context lines with the
+    /// same content in both hunks. Otherwise the patch is left untouched and the error refers to
+    /// the first hunk, in sorted order, which couldn't be merged.
+    pub fn repair_overlaps(&mut self) -> Result<(), RepairError>
+    where
+        T: PartialEq,
+    {
+        let mut hunks = self.hunks.clone();
+        hunks.sort_by_key(|hunk| hunk.old_range.position());
+
+        let mut repaired: Vec<Hunk<'a, T>> = Vec::with_capacity(hunks.len());
+        for (i, hunk) in hunks.into_iter().enumerate() {
+            let overlap = match repaired.last() {
+                Some(prev) => (prev.old_range.position() + prev.old_range.len)
+                    .saturating_sub(hunk.old_range.position()),
+                None => 0,
+            };
+            if overlap == 0 {
+                repaired.push(hunk);
+                continue;
+            }
+
+            let prev = repaired.last_mut().unwrap();
+            let shared = match (
+                prev.lines.len().checked_sub(overlap),
+                hunk.lines.get(..overlap),
+            ) {
+                (Some(start), Some(shared)) if prev.lines[start..] == *shared => shared,
+                _ => return Err(RepairError(i + 1)),
+            };
+            if !shared.iter().all(|line| matches!(line, Line::Context(_))) {
+                return Err(RepairError(i + 1));
+            }
+
+            prev.lines.extend_from_slice(&hunk.lines[overlap..]);
+            let (old_len, new_len) = hunk_lines_count(&prev.lines);
+            prev.old_range = HunkRange::from_position(prev.old_range.position(), old_len);
+            prev.new_range = HunkRange::from_position(prev.new_range.position(), new_len);
+        }
+
+        self.hunks = repaired;
+        Ok(())
+    }
+
+    /// Keep only the first `max` hunks, returning the number of hunks which were dropped
+    ///
+    /// Hunks are kept in their current order, use [`sort_hunks`](Patch::sort_hunks) first if
+    /// they may be out of order.
+    pub fn truncate_hunks(&mut self, max: usize) -> usize {
+        let dropped = self.hunks.len().saturating_sub(max);
+        self.hunks.truncate(max);
+        dropped
+    }
+
+    /// Check that the hunk ranges agree with the lines contained in each hunk
+    /// and that the hunks are in order and don't overlap
+    pub fn validate(&self) -> Result<(), ParsePatchError> {
+        for hunk in &self.hunks {
+            let (old_len, new_len) = hunk_lines_count(&hunk.lines);
+            if old_len != hunk.old_range.len || new_len != hunk.new_range.len {
+                return Err(ParsePatchError::new("Hunk header does not match hunk"));
+            }
+        }
+
+        if !parse::verify_hunks_in_order(&self.hunks) {
+            return Err(ParsePatchError::new("Hunks not in order or overlap"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of inserted, deleted and context lines across all hunks
+    pub fn stats(&self) -> DiffStats {
+        self.hunks.iter().map(Hunk::stats).sum()
+    }
+
+    /// Returns a new `Patch`, with the same filenames as this one, which only
+    /// contains the hunk at `index`, or `None` if `index` is out of bounds.
+    ///
+    /// This is useful for applying or sharing a single hunk in isolation.
+    pub fn hunk_as_patch(&self, index: usize) -> Option<Patch<'a, T>> {
+        let hunk = self.hunks.get(index)?.clone();
+        Some(Patch {
+            original: self.original.clone(),
+            modified: self.modified.clone(),
+            hunks: vec![hunk],
+            index: None,
+            binary: None,
+        })
+    }
+
+    /// Returns the hunks whose changes are only made by one of `self` and `other`
+    ///
+    /// Hunks are compared by their inserted and deleted lines and where they occur in the old
+    /// file, ignoring context lines, so two patches making the same changes with a different
+    /// amount of context don't diverge. Divergences are ordered by their position in the old
+    /// file.
+    ///
+    /// ```
+    /// use diffy::{create_patch, DiffOptions, HunkDivergence};
+    ///
+    /// let original = "a\nb\nc\nd\n";
+    /// let patch = create_patch(original, "a\nB\nc\nd\n");
+    /// let minimal = DiffOptions::new()
+    ///     .set_context_len(0)
+    ///     .create_patch(original, "a\nB\nc\nd\n");
+    /// assert!(patch.diff_against(&minimal).is_empty());
+    ///
+    /// let other = create_patch(original, "a\nb\nc\nD\n");
+    /// assert_eq!(
+    ///     patch.diff_against(&other),
+    ///     [
+    ///         HunkDivergence::OnlyInSelf(&patch.hunks()[0]),
+    ///         HunkDivergence::OnlyInOther(&other.hunks()[0]),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff_against<'p>(&'p self, other: &'p Patch<'a, T>) -> Vec<HunkDivergence<'p, 'a, T>>
+    where
+        T: PartialEq,
+    {
+        let changes: Vec<_> = self.hunks.iter().map(Hunk::changes).collect();
+        let other_changes: Vec<_> = other.hunks.iter().map(Hunk::changes).collect();
+
+        let mut divergences: Vec<_> = self
+            .hunks
+            .iter()
+            .zip(&changes)
+            .filter(|(_, c)| !other_changes.contains(c))
+            .map(|(hunk, _)| HunkDivergence::OnlyInSelf(hunk))
+            .chain(
+                other
+                    .hunks
+                    .iter()
+                    .zip(&other_changes)
+                    .filter(|(_, c)| !changes.contains(c))
+                    .map(|(hunk, _)| HunkDivergence::OnlyInOther(hunk)),
+            )
+            .collect();
+        divergences.sort_by_key(|divergence| divergence.hunk().old_range().position());
+        divergences
+    }
+
+    /// Returns a `Patch` undoing the changes of this one
+    ///
+    /// A [`binary`](Patch::binary) section can't be reversed and is dropped.
     pub fn reverse(&self) -> Patch<'_, T> {
         let hunks = self.hunks.iter().map(Hunk::reverse).collect();
         Patch {
             original: self.modified.clone(),
             modified: self.original.clone(),
             hunks,
+            index: self.index.as_deref().map(IndexLine::reverse).map(Box::new),
+            binary: None,
         }
     }
 }
@@ -70,11 +325,35 @@ impl<T: AsRef<[u8]> + ToOwned + ?Sized> Patch<'_, T> {
     /// potentially non-utf8 patches.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
-        PatchFormatter::new()
-            .write_patch_into(self, &mut bytes)
-            .unwrap();
+        self.write_to(&mut bytes).unwrap();
         bytes
     }
+
+    /// Write a `Patch` into a writer `W: io::Write`
+    ///
+    /// Unlike going through `to_string` or `to_bytes`, this doesn't require
+    /// buffering the whole patch in memory before writing it out.
+    pub fn write_to<W: io::Write>(&self, w: W) -> io::Result<()> {
+        PatchFormatter::new().write_patch_into(self, w)
+    }
+
+    /// Returns `true` if every change in the patch only adds, removes or changes whitespace
+    ///
+    /// Each run of deleted and inserted lines is compared with all ASCII whitespace, including
+    /// newlines, removed. A patch without any changes is considered whitespace only.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("if x {\n\ty();\n}\n", "if x {\n    y();\n}\n\n");
+    /// assert!(patch.is_whitespace_only());
+    ///
+    /// let patch = create_patch("if x {\n\ty();\n}\n", "if x {\n    z();\n}\n");
+    /// assert!(!patch.is_whitespace_only());
+    /// ```
+    pub fn is_whitespace_only(&self) -> bool {
+        self.hunks.iter().all(Hunk::is_whitespace_only)
+    }
 }
 
 impl<'a> Patch<'a, str> {
@@ -101,6 +380,249 @@ impl<'a> Patch<'a, str> {
     pub fn from_str(s: &'a str) -> Result<Patch<'a, str>, ParsePatchError> {
         parse::parse(s)
     }
+
+    /// Parse a `Patch` from a diff in the "normal" format, as produced by `diff` without `-u`
+    ///
+    /// The resulting hunks contain no context lines and the patch has no filenames.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let s = "\
+    /// 2c2
+    /// < two
+    /// ---
+    /// > 2
+    /// 4a5
+    /// > five
+    /// ";
+    ///
+    /// let patch = Patch::from_normal_str(s).unwrap();
+    /// assert_eq!(patch.hunks().len(), 2);
+    /// assert_eq!(patch.to_normal_string(), s);
+    /// ```
+    pub fn from_normal_str(s: &'a str) -> Result<Patch<'a, str>, ParsePatchError> {
+        normal::parse_normal(s)
+    }
+
+    /// Render the patch in the "normal" diff format, as produced by `diff` without `-u`
+    ///
+    /// Context lines aren't part of the format and are dropped. Filenames are not rendered.
+    pub fn to_normal_string(&self) -> String {
+        normal::to_normal_string(self)
+    }
+
+    /// Render the patch as an `ed` script, as produced by `diff -e` and applied by `patch -e`
+    ///
+    /// The commands are ordered from the end of the file to its start. Context lines and
+    /// filenames aren't part of the format, and neither is a missing newline at the end of the
+    /// file, so inserted lines are always terminated by a newline.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb\nc\nd\n", "a\nB\nc\n");
+    /// assert_eq!(patch.to_ed_script(), "4d\n2c\nB\n.\n");
+    /// ```
+    pub fn to_ed_script(&self) -> String {
+        normal::to_ed_script(self)
+    }
+
+    /// Render a summary of the patch in the style of `git diff --stat`
+    ///
+    /// The first line contains the name of the file without git's `a/` or `b/` prefix, the
+    /// number of changed lines and a graph of `+` and `-` characters, scaled down if needed so
+    /// that the whole line fits in `width` columns. The second line contains the totals.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("one\ntwo\n", "one\n2\n3\n");
+    /// assert_eq!(
+    ///     patch.stat_summary(80),
+    ///     " modified | 3 ++-\n 1 file changed, 2 insertions(+), 1 deletion(-)\n"
+    /// );
+    /// ```
+    pub fn stat_summary(&self, width: usize) -> String {
+        let name = self.display_name().unwrap_or_default();
+        stat_summary(&[(name, self.stats())], width)
+    }
+
+    /// Convert the patch into an [`OwnedPatch`], which doesn't borrow from the texts it was
+    /// created or parsed from
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original = String::from("a\nb\n");
+    /// let modified = String::from("a\nB\n");
+    /// let patch = create_patch(&original, &modified);
+    /// let expected = patch.to_string();
+    ///
+    /// let owned = patch.into_owned();
+    /// drop((original, modified));
+    /// assert_eq!(owned.to_string(), expected);
+    /// ```
+    pub fn into_owned(self) -> OwnedPatch {
+        OwnedPatch::new(self)
+    }
+
+    /// Render a one line summary of the patch, e.g. for use in a log or commit message
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("one\ntwo\n", "one\n2\n3\n");
+    /// assert_eq!(patch.summary(), "1 hunk, +2 -1 across modified");
+    /// ```
+    pub fn summary(&self) -> String {
+        let stats = self.stats();
+        let plural = if self.hunks.len() == 1 { "" } else { "s" };
+        let mut summary = format!(
+            "{} hunk{}, +{} -{}",
+            self.hunks.len(),
+            plural,
+            stats.insertions,
+            stats.deletions
+        );
+        if let Some(name) = self.display_name() {
+            summary.push_str(" across ");
+            summary.push_str(name);
+        }
+        summary
+    }
+
+    /// Returns the replacement text for each run of changed lines, as needed for GitHub review
+    /// suggestions
+    ///
+    /// Each run of deleted and inserted lines between context lines becomes one
+    /// [`Suggestion`], whose display is the fenced `suggestion` block to post on its old range.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb\nc\nd\n", "a\nB\nC\nd\n");
+    /// let suggestions = patch.to_suggestions();
+    /// assert_eq!(suggestions[0].old_range().range(), 2..4);
+    /// assert_eq!(suggestions[0].replacement(), "B\nC\n");
+    /// assert_eq!(suggestions[0].to_string(), "```suggestion\nB\nC\n```\n");
+    /// ```
+    pub fn to_suggestions(&self) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+        for hunk in &self.hunks {
+            let mut position = hunk.old_range.position();
+            // The position and number of deleted lines of the current run of changes, along
+            // with the lines replacing them
+            let mut change: Option<(usize, usize, String)> = None;
+
+            for line in &hunk.lines {
+                match line {
+                    Line::Context(_) => {
+                        suggestions.extend(change.take().map(Suggestion::new));
+                        position += 1;
+                    }
+                    Line::Delete(_) => {
+                        change.get_or_insert_with(|| (position, 0, String::new())).1 += 1;
+                        position += 1;
+                    }
+                    Line::Insert(line) => change
+                        .get_or_insert_with(|| (position, 0, String::new()))
+                        .2
+                        .push_str(line),
+                }
+            }
+            suggestions.extend(change.map(Suggestion::new));
+        }
+        suggestions
+    }
+
+    // The name of the file the patch is for, preferring the modified filename unless the file
+    // was deleted
+    fn display_name(&self) -> Option<&str> {
+        self.modified()
+            .filter(|name| *name != "/dev/null")
+            .or_else(|| self.original())
+    }
+}
+
+// Renders a `git diff --stat` style summary of the files with the given names and stats. The
+// graphs of all files are scaled alike, so that the longest line fits in `width` columns.
+fn stat_summary(files: &[(&str, DiffStats)], width: usize) -> String {
+    // Like git, leave out the `a/` and `b/` prefixes of the filenames
+    let files: Vec<_> = files
+        .iter()
+        .map(|&(name, stats)| {
+            let name = name
+                .strip_prefix("a/")
+                .or_else(|| name.strip_prefix("b/"))
+                .unwrap_or(name);
+            (name, stats)
+        })
+        .collect();
+
+    let changed = |stats: &DiffStats| stats.insertions + stats.deletions;
+    let name_width = files.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let max_changed = files
+        .iter()
+        .map(|(_, stats)| changed(stats))
+        .max()
+        .unwrap_or(0);
+    let count_width = max_changed.to_string().len();
+
+    // " {name} | {count} {graph}"
+    let graph_width = width.saturating_sub(name_width + count_width + 5);
+    let scale = |n: usize| {
+        if max_changed <= graph_width || n == 0 {
+            n
+        } else {
+            1 + n * graph_width.saturating_sub(1) / max_changed
+        }
+    };
+    // The whole graph of a file is scaled at once so that it fits, then split into the scaled
+    // smaller side and the rest. Both sides keep at least one character if there's room.
+    let graph = |stats: &DiffStats| {
+        let mut len = scale(changed(stats));
+        if stats.insertions > 0 && stats.deletions > 0 {
+            len = cmp::max(len, cmp::min(2, graph_width));
+        }
+        let (insertions, deletions) = if stats.insertions < stats.deletions {
+            let insertions = cmp::min(scale(stats.insertions), len);
+            (insertions, len - insertions)
+        } else {
+            let deletions = cmp::min(scale(stats.deletions), len);
+            (len - deletions, deletions)
+        };
+        format!("{}{}", "+".repeat(insertions), "-".repeat(deletions))
+    };
+
+    let mut summary = String::new();
+    let mut total = DiffStats::default();
+    for (name, stats) in &files {
+        summary.push_str(&format!(
+            " {:<name_width$} | {:>count_width$} {}\n",
+            name,
+            changed(stats),
+            graph(stats),
+            name_width = name_width,
+            count_width = count_width,
+        ));
+        total.insertions += stats.insertions;
+        total.deletions += stats.deletions;
+    }
+
+    let plural = if files.len() == 1 { "" } else { "s" };
+    summary.push_str(&format!(" {} file{} changed", files.len(), plural));
+    if total.insertions > 0 || total.deletions == 0 {
+        let plural = if total.insertions == 1 { "" } else { "s" };
+        summary.push_str(&format!(", {} insertion{}(+)", total.insertions, plural));
+    }
+    if total.deletions > 0 || total.insertions == 0 {
+        let plural = if total.deletions == 1 { "" } else { "s" };
+        summary.push_str(&format!(", {} deletion{}(-)", total.deletions, plural));
+    }
+    summary.push('\n');
+
+    summary
 }
 
 impl<'a> Patch<'a, [u8]> {
@@ -116,10 +638,55 @@ impl<T: ToOwned + ?Sized> Clone for Patch<'_, T> {
             original: self.original.clone(),
             modified: self.modified.clone(),
             hunks: self.hunks.clone(),
+            index: self.index.clone(),
+            binary: self.binary.clone(),
         }
     }
 }
 
+impl<'a, T: ToOwned + ?Sized> IntoIterator for Patch<'a, T> {
+    type Item = Hunk<'a, T>;
+    type IntoIter = std::vec::IntoIter<Hunk<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hunks.into_iter()
+    }
+}
+
+impl<'p, 'a, T: ToOwned + ?Sized> IntoIterator for &'p Patch<'a, T> {
+    type Item = &'p Hunk<'a, T>;
+    type IntoIter = std::slice::Iter<'p, Hunk<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hunks.iter()
+    }
+}
+
+/// Appends hunks to the patch as they are
+///
+/// Use [`sort_hunks`](Patch::sort_hunks) and [`renumber`](Patch::renumber) afterwards if the new
+/// hunks aren't already in order or their ranges don't account for the preceding hunks.
+///
+/// ```
+/// use diffy::{create_patch, Patch};
+///
+/// let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+/// let first = create_patch(original, "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\n");
+/// let second = create_patch(original, "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n");
+///
+/// let mut patch = Patch::from_str("").unwrap();
+/// patch.extend(second.into_iter().chain(first));
+/// patch.sort_hunks();
+/// patch.renumber();
+/// assert!(patch.validate().is_ok());
+/// assert_eq!(patch.hunks().len(), 2);
+/// ```
+impl<'a, T: ToOwned + ?Sized> Extend<Hunk<'a, T>> for Patch<'a, T> {
+    fn extend<I: IntoIterator<Item = Hunk<'a, T>>>(&mut self, iter: I) {
+        self.hunks.extend(iter)
+    }
+}
+
 impl fmt::Display for Patch<'_, str> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", PatchFormatter::new().fmt_patch(self))
@@ -136,6 +703,8 @@ where
             .field("original", &self.original)
             .field("modified", &self.modified)
             .field("hunks", &self.hunks)
+            .field("index", &self.index)
+            .field("binary", &self.binary)
             .finish()
     }
 }
@@ -144,7 +713,7 @@ where
 struct Filename<'a, T: ToOwned + ?Sized>(Cow<'a, T>);
 
 const ESCAPED_CHARS: &[char] = &['\n', '\t', '\0', '\r', '\"', '\\'];
-const ESCAPED_CHARS_BYTES: &[u8] = &[b'\n', b'\t', b'\0', b'\r', b'\"', b'\\'];
+const ESCAPED_CHARS_BYTES: &[u8] = b"\n\t\0\r\"\\";
 
 impl Filename<'_, str> {
     fn needs_to_be_escaped(&self) -> bool {
@@ -229,6 +798,52 @@ where
     }
 }
 
+/// The `index <old>..<new> [<mode>]` line of a git patch, see [`Patch::index`]
+///
+/// It holds the abbreviated object ids of the old and new file and, if the file mode didn't
+/// change, the file mode. Its display is the line itself, without a line terminator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexLine {
+    old_id: String,
+    new_id: String,
+    mode: Option<String>,
+}
+
+impl IndexLine {
+    /// Returns the object id of the old file
+    pub fn old_id(&self) -> &str {
+        &self.old_id
+    }
+
+    /// Returns the object id of the new file
+    pub fn new_id(&self) -> &str {
+        &self.new_id
+    }
+
+    /// Returns the mode of the file, if it's part of the line
+    pub fn mode(&self) -> Option<&str> {
+        self.mode.as_deref()
+    }
+
+    fn reverse(&self) -> Self {
+        Self {
+            old_id: self.new_id.clone(),
+            new_id: self.old_id.clone(),
+            mode: self.mode.clone(),
+        }
+    }
+}
+
+impl fmt::Display for IndexLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "index {}..{}", self.old_id, self.new_id)?;
+        if let Some(mode) = &self.mode {
+            write!(f, " {}", mode)?;
+        }
+        Ok(())
+    }
+}
+
 /// Represents a group of differing lines between two files
 #[derive(Debug, PartialEq, Eq)]
 pub struct Hunk<'a, T: ?Sized> {
@@ -283,11 +898,124 @@ impl<'a, T: ?Sized> Hunk<'a, T> {
         self.function_context
     }
 
+    /// Returns a mutable reference to the corresponding range for the old file in the hunk
+    pub fn old_range_mut(&mut self) -> &mut HunkRange {
+        &mut self.old_range
+    }
+
+    /// Returns a mutable reference to the corresponding range for the new file in the hunk
+    pub fn new_range_mut(&mut self) -> &mut HunkRange {
+        &mut self.new_range
+    }
+
     /// Returns the lines in the hunk
     pub fn lines(&self) -> &[Line<'a, T>] {
         &self.lines
     }
 
+    /// Returns the lines in the hunk along with their 1-based line numbers in the old and new
+    /// files
+    ///
+    /// Inserted lines have no old line number and deleted lines have no new line number.
+    ///
+    /// ```
+    /// use diffy::{Line, Patch};
+    ///
+    /// let patch = Patch::from_str("--- a\n+++ b\n@@ -3,2 +3,2 @@\n x\n-y\n+z\n").unwrap();
+    /// let numbered: Vec<_> = patch.hunks()[0].numbered_lines().collect();
+    /// assert_eq!(
+    ///     numbered,
+    ///     [
+    ///         (Some(3), Some(3), Line::Context("x\n")),
+    ///         (Some(4), None, Line::Delete("y\n")),
+    ///         (None, Some(4), Line::Insert("z\n")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn numbered_lines(
+        &self,
+    ) -> impl Iterator<Item = (Option<usize>, Option<usize>, Line<'a, T>)> + '_ {
+        let mut old_line = self.old_range.position() + 1;
+        let mut new_line = self.new_range.position() + 1;
+        self.lines.iter().map(move |&line| {
+            let (old, new) = match line {
+                Line::Context(_) => (Some(old_line), Some(new_line)),
+                Line::Delete(_) => (Some(old_line), None),
+                Line::Insert(_) => (None, Some(new_line)),
+            };
+            old_line += old.is_some() as usize;
+            new_line += new.is_some() as usize;
+            (old, new, line)
+        })
+    }
+
+    // Returns the inserted and deleted lines of the hunk along with the 0-based index of the
+    // line in the old file they come before
+    fn changes(&self) -> Vec<(usize, Line<'a, T>)> {
+        let mut old_line = self.old_range.position();
+        let mut changes = Vec::new();
+        for &line in &self.lines {
+            match line {
+                Line::Context(_) => old_line += 1,
+                Line::Delete(_) => {
+                    changes.push((old_line, line));
+                    old_line += 1;
+                }
+                Line::Insert(_) => changes.push((old_line, line)),
+            }
+        }
+        changes
+    }
+
+    /// Returns a mutable reference to the lines in the hunk
+    ///
+    /// Modifying the lines can leave the hunk's ranges out of date, see
+    /// [`Patch::renumber`](struct.Patch.html#method.renumber).
+    pub fn lines_mut(&mut self) -> &mut Vec<Line<'a, T>> {
+        &mut self.lines
+    }
+
+    /// Returns the number of inserted, deleted and context lines in the hunk
+    pub fn stats(&self) -> DiffStats {
+        self.lines
+            .iter()
+            .fold(DiffStats::default(), |mut stats, line| {
+                match line {
+                    Line::Context(_) => stats.context += 1,
+                    Line::Delete(_) => stats.deletions += 1,
+                    Line::Insert(_) => stats.insertions += 1,
+                }
+                stats
+            })
+    }
+
+    /// Returns a copy of the hunk with at most `context_len` leading and
+    /// trailing context lines, adjusting its ranges accordingly.
+    ///
+    /// Context lines between changes are left untouched since removing them
+    /// would require splitting the hunk.
+    pub fn trim_context(&self, context_len: usize) -> Self {
+        let is_context = |line: &&Line<'a, T>| matches!(line, Line::Context(_));
+        let leading = self.lines.iter().take_while(is_context).count();
+        let trailing = if leading == self.lines.len() {
+            0
+        } else {
+            self.lines.iter().rev().take_while(is_context).count()
+        };
+
+        let front = leading.saturating_sub(context_len);
+        let back = trailing.saturating_sub(context_len);
+        let lines = self.lines[front..self.lines.len() - back].to_vec();
+        let (old_len, new_len) = hunk_lines_count(&lines);
+
+        Self {
+            old_range: HunkRange::from_position(self.old_range.position() + front, old_len),
+            new_range: HunkRange::from_position(self.new_range.position() + front, new_len),
+            function_context: self.function_context,
+            lines,
+        }
+    }
+
     /// Creates a reverse patch for the hunk.  This is equivalent to what
     /// XDL_PATCH_REVERSE would apply in libxdiff.
     pub fn reverse(&self) -> Self {
@@ -301,6 +1029,75 @@ impl<'a, T: ?Sized> Hunk<'a, T> {
     }
 }
 
+impl<T: AsRef<[u8]> + ?Sized> Hunk<'_, T> {
+    /// Returns `true` if every change in the hunk only adds, removes or changes whitespace, see
+    /// [`Patch::is_whitespace_only`]
+    pub fn is_whitespace_only(&self) -> bool {
+        fn non_whitespace<'a, T, I>(lines: I) -> impl Iterator<Item = u8> + 'a
+        where
+            T: AsRef<[u8]> + ?Sized + 'a,
+            I: Iterator<Item = &'a T> + 'a,
+        {
+            lines
+                .flat_map(|line| line.as_ref().iter().copied())
+                .filter(|byte| !byte.is_ascii_whitespace())
+        }
+
+        self.lines
+            .split(|line| matches!(line, Line::Context(_)))
+            .all(|change| {
+                let deleted = change.iter().filter_map(|line| match line {
+                    Line::Delete(l) => Some(*l),
+                    _ => None,
+                });
+                let inserted = change.iter().filter_map(|line| match line {
+                    Line::Insert(l) => Some(*l),
+                    _ => None,
+                });
+                non_whitespace(deleted).eq(non_whitespace(inserted))
+            })
+    }
+}
+
+impl<'a, T: Text + ?Sized> Hunk<'a, T> {
+    /// Returns a copy of the hunk with up to `before` additional context lines
+    /// prepended and up to `after` additional context lines appended.
+    ///
+    /// The context lines are taken from `original`, the text the hunk applies
+    /// to, and are limited by the beginning and end of both files. Lines
+    /// missing from `original`, e.g. because it's shorter than the hunk's old
+    /// range, aren't added. Expanding a hunk past its neighbours in a `Patch`
+    /// results in overlapping hunks.
+    pub fn expand_context(&self, original: &'a T, before: usize, after: usize) -> Self {
+        let original: Vec<_> = LineIter::new(original).collect();
+        let start = self.old_range.position();
+        let end = start + self.old_range.len;
+
+        // Earlier hunks may have removed lines, so the new file can start closer to the hunk
+        let before = cmp::min(before, cmp::min(start, self.new_range.position()));
+        let after = cmp::min(after, original.len().saturating_sub(end));
+        let leading = original.get(start - before..start).unwrap_or_default();
+        let trailing = original.get(end..end + after).unwrap_or_default();
+        let before = leading.len();
+
+        let lines = leading
+            .iter()
+            .copied()
+            .map(Line::Context)
+            .chain(self.lines.iter().copied())
+            .chain(trailing.iter().copied().map(Line::Context))
+            .collect::<Vec<_>>();
+        let (old_len, new_len) = hunk_lines_count(&lines);
+
+        Self {
+            old_range: HunkRange::from_position(start - before, old_len),
+            new_range: HunkRange::from_position(self.new_range.position() - before, new_len),
+            function_context: self.function_context,
+            lines,
+        }
+    }
+}
+
 impl<T: ?Sized> Clone for Hunk<'_, T> {
     fn clone(&self) -> Self {
         Self {
@@ -312,7 +1109,139 @@ impl<T: ?Sized> Clone for Hunk<'_, T> {
     }
 }
 
+/// Statistics about the lines in a `Patch` or `Hunk`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    insertions: usize,
+    deletions: usize,
+    context: usize,
+}
+
+impl DiffStats {
+    /// Returns the number of inserted lines
+    pub fn insertions(&self) -> usize {
+        self.insertions
+    }
+
+    /// Returns the number of deleted lines
+    pub fn deletions(&self) -> usize {
+        self.deletions
+    }
+
+    /// Returns the number of context lines
+    pub fn context(&self) -> usize {
+        self.context
+    }
+}
+
+/// An error returned when [`Patch::repair_overlaps`] can't merge two overlapping hunks
+#[derive(Debug)]
+pub struct RepairError(usize);
+
+impl fmt::Display for RepairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "conflicting overlap at hunk #{}", self.0)
+    }
+}
+
+impl std::error::Error for RepairError {}
+
+/// The replacement for a run of changed lines, see [`Patch::to_suggestions`]
+///
+/// Its display is a GitHub review suggestion: a fenced `suggestion` block holding the
+/// replacement, to be posted on the lines in its old range. A pure insertion has an empty old
+/// range, which has to be widened to a neighboring line before posting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    old_range: HunkRange,
+    replacement: String,
+}
+
+impl Suggestion {
+    fn new((position, len, replacement): (usize, usize, String)) -> Self {
+        Self {
+            old_range: HunkRange::from_position(position, len),
+            replacement,
+        }
+    }
+
+    /// Returns the range of lines in the old file which are replaced, which is empty for a pure
+    /// insertion
+    pub fn old_range(&self) -> HunkRange {
+        self.old_range
+    }
+
+    /// Returns the lines replacing the old range, which is empty for a pure deletion
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The fence has to be longer than any run of backticks in the replacement
+        let longest_run = self
+            .replacement
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(cmp::max(3, longest_run + 1));
+
+        writeln!(f, "{}suggestion", fence)?;
+        f.write_str(&self.replacement)?;
+        if !self.replacement.is_empty() && !self.replacement.ends_with('\n') {
+            writeln!(f)?;
+        }
+        writeln!(f, "{}", fence)
+    }
+}
+
+/// A hunk whose changes are only made by one of two patches, see [`Patch::diff_against`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum HunkDivergence<'p, 'a, T: ?Sized> {
+    /// A hunk only found in the patch `diff_against` was called on
+    OnlyInSelf(&'p Hunk<'a, T>),
+    /// A hunk only found in the patch passed to `diff_against`
+    OnlyInOther(&'p Hunk<'a, T>),
+}
+
+impl<'p, 'a, T: ?Sized> HunkDivergence<'p, 'a, T> {
+    /// Returns the diverging hunk
+    pub fn hunk(&self) -> &'p Hunk<'a, T> {
+        match self {
+            HunkDivergence::OnlyInSelf(hunk) | HunkDivergence::OnlyInOther(hunk) => hunk,
+        }
+    }
+}
+
+impl ops::Add for DiffStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            insertions: self.insertions + other.insertions,
+            deletions: self.deletions + other.deletions,
+            context: self.context + other.context,
+        }
+    }
+}
+
+impl std::iter::Sum for DiffStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), ops::Add::add)
+    }
+}
+
 /// The range of lines in a file for a particular `Hunk`.
+///
+/// Line numbers are 1-based. Following the unified format, an empty range has the number of
+/// the line *preceding* it as its start, so an empty range at the very beginning of a file has
+/// a start of `0`.
+///
+/// When displayed, the length is omitted if it is `1` and always included otherwise, e.g. an
+/// empty range at the beginning of a file is rendered as `0,0` and an insertion after line 11
+/// as `11,0` on the old side.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct HunkRange {
     /// The starting line number of a hunk
@@ -326,6 +1255,38 @@ impl HunkRange {
         Self { start, len }
     }
 
+    // Create a range from the 0-based index of its first line. Following the unified format, an
+    // empty range refers to the line preceding it instead.
+    fn from_position(position: usize, len: usize) -> Self {
+        let start = if len > 0 { position + 1 } else { position };
+        Self::new(start, len)
+    }
+
+    // Returns the 0-based index of the first line of the range, see `from_position`
+    pub(crate) fn position(&self) -> usize {
+        if self.len > 0 {
+            self.start.saturating_sub(1)
+        } else {
+            self.start
+        }
+    }
+
+    /// Parse the old and new ranges from a hunk header line
+    ///
+    /// Both the compact form, where a length of `1` is omitted, and a trailing
+    /// section heading after the closing `@@` are accepted.
+    ///
+    /// ```
+    /// use diffy::HunkRange;
+    ///
+    /// let (old, new) = HunkRange::parse("@@ -4 +2,2 @@ fn main() {").unwrap();
+    /// assert_eq!(old.range(), 4..5);
+    /// assert_eq!(new.range(), 2..4);
+    /// ```
+    pub fn parse(header: &str) -> Result<(HunkRange, HunkRange), ParsePatchError> {
+        parse::hunk_header(header).map(|(old_range, new_range, _)| (old_range, new_range))
+    }
+
     /// Returns the range as a `ops::Range`
     pub fn range(&self) -> ops::Range<usize> {
         self.start..self.end()
@@ -350,6 +1311,16 @@ impl HunkRange {
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
+
+    /// Set the starting line number of the range
+    pub fn set_start(&mut self, start: usize) {
+        self.start = start;
+    }
+
+    /// Set the number of lines in the range
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
 }
 
 impl fmt::Display for HunkRange {
@@ -366,6 +1337,22 @@ impl fmt::Display for HunkRange {
 ///
 /// A `Line` contains the terminating newline character `\n` unless it is the final
 /// line in the file and the file does not end with a newline character.
+///
+/// This is how a `Patch` records a missing newline at the end of a file: such a line is written
+/// followed by a `\ No newline at end of file` marker, and parsing the marker strips the newline
+/// from the line before it again.
+///
+/// ```
+/// use diffy::{create_patch, Line, Patch};
+///
+/// let patch = create_patch("a\nb\n", "a\nc");
+/// let s = patch.to_string();
+/// assert!(s.ends_with("+c\n\\ No newline at end of file\n"));
+///
+/// let parsed = Patch::from_str(&s).unwrap();
+/// assert_eq!(parsed.hunks()[0].lines().last(), Some(&Line::Insert("c")));
+/// assert_eq!(parsed, patch);
+/// ```
 #[derive(Debug, PartialEq, Eq)]
 pub enum Line<'a, T: ?Sized> {
     /// A line providing context in the diff which is present in both the old and new file
@@ -384,7 +1371,34 @@ impl<T: ?Sized> Clone for Line<'_, T> {
     }
 }
 
-impl<T: ?Sized> Line<'_, T> {
+/// The kind of a [`Line`], without its content
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    /// See [`Line::Context`]
+    Context,
+    /// See [`Line::Delete`]
+    Delete,
+    /// See [`Line::Insert`]
+    Insert,
+}
+
+impl<'a, T: ?Sized> Line<'a, T> {
+    /// Returns the kind of the line
+    pub fn kind(&self) -> LineKind {
+        match self {
+            Line::Context(_) => LineKind::Context,
+            Line::Delete(_) => LineKind::Delete,
+            Line::Insert(_) => LineKind::Insert,
+        }
+    }
+
+    /// Returns the content of the line, including its line ending
+    pub fn content(&self) -> &'a T {
+        match self {
+            Line::Context(s) | Line::Delete(s) | Line::Insert(s) => s,
+        }
+    }
+
     pub fn reverse(&self) -> Self {
         match self {
             Line::Context(s) => Line::Context(s),
@@ -0,0 +1,237 @@
+// Support for the "normal" diff format, the default output of `diff` without `-u`, and for the
+// `ed` scripts produced by `diff -e`
+
+use super::{
+    parse::verify_hunks_in_order, Hunk, HunkRange, Line, ParsePatchError, Patch, NO_NEWLINE_AT_EOF,
+};
+use crate::utils::LineIter;
+use std::{fmt::Write, iter::Peekable};
+
+type Result<T, E = ParsePatchError> = std::result::Result<T, E>;
+
+pub fn parse_normal(input: &str) -> Result<Patch<'_, str>> {
+    let mut lines = LineIter::new(input).peekable();
+    let mut hunks = Vec::new();
+
+    while let Some(command) = lines.next() {
+        hunks.push(hunk(command, &mut lines)?);
+    }
+
+    if !verify_hunks_in_order(&hunks) {
+        return Err(ParsePatchError::new("Hunks not in order or overlap"));
+    }
+    Ok(Patch::new(None::<&str>, None::<&str>, hunks))
+}
+
+fn hunk<'a, I: Iterator<Item = &'a str>>(
+    command: &'a str,
+    lines: &mut Peekable<I>,
+) -> Result<Hunk<'a, str>> {
+    let command = command.strip_suffix('\n').unwrap_or(command);
+    let idx = command
+        .find(['a', 'c', 'd'])
+        .ok_or_else(|| ParsePatchError::new("unable to parse normal diff command"))?;
+    let (old, new) = (&command[..idx], &command[idx + 1..]);
+
+    let (old_range, new_range) = match &command[idx..=idx] {
+        "a" => (HunkRange::new(line_number(old)?, 0), range(new)?),
+        "d" => (range(old)?, HunkRange::new(line_number(new)?, 0)),
+        _ => (range(old)?, range(new)?),
+    };
+
+    let mut hunk_lines = Vec::new();
+    changed_lines(lines, "< ", old_range.len(), Line::Delete, &mut hunk_lines)?;
+    if !old_range.is_empty() && !new_range.is_empty() {
+        match lines.next() {
+            Some("---\n") | Some("---") => {}
+            _ => return Err(ParsePatchError::new("expected '---' separator")),
+        }
+    }
+    changed_lines(lines, "> ", new_range.len(), Line::Insert, &mut hunk_lines)?;
+
+    Ok(Hunk::new(old_range, new_range, None, hunk_lines))
+}
+
+fn changed_lines<'a, I: Iterator<Item = &'a str>>(
+    lines: &mut Peekable<I>,
+    prefix: &str,
+    count: usize,
+    kind: fn(&'a str) -> Line<'a, str>,
+    hunk_lines: &mut Vec<Line<'a, str>>,
+) -> Result<()> {
+    for _ in 0..count {
+        let line = lines
+            .next()
+            .and_then(|line| line.strip_prefix(prefix))
+            .ok_or_else(|| ParsePatchError::new("missing or malformed changed line"))?;
+
+        let line = match lines.peek() {
+            Some(next) if next.starts_with(NO_NEWLINE_AT_EOF) => {
+                lines.next();
+                line.strip_suffix('\n').unwrap_or(line)
+            }
+            _ => line,
+        };
+        hunk_lines.push(kind(line));
+    }
+
+    Ok(())
+}
+
+fn line_number(s: &str) -> Result<usize> {
+    s.parse()
+        .map_err(|_| ParsePatchError::new("can't parse line number"))
+}
+
+// Parses `N` or `N,M`, both inclusive
+fn range(s: &str) -> Result<HunkRange> {
+    let (start, end) = match s.split_once(',') {
+        Some((start, end)) => (line_number(start)?, line_number(end)?),
+        None => (line_number(s)?, line_number(s)?),
+    };
+
+    if start == 0 || end < start {
+        return Err(ParsePatchError::new("invalid line range"));
+    }
+    Ok(HunkRange::new(start, end - start + 1))
+}
+
+pub fn to_normal_string(patch: &Patch<'_, str>) -> String {
+    let mut output = String::new();
+
+    for change in changes(patch) {
+        let command = change.command();
+        writeln!(
+            output,
+            "{}{}{}",
+            normal_range(change.old_position, change.deleted.len()),
+            command,
+            normal_range(change.new_position, change.inserted.len())
+        )
+        .unwrap();
+
+        push_lines(&mut output, "< ", &change.deleted);
+        if command == 'c' {
+            output.push_str("---\n");
+        }
+        push_lines(&mut output, "> ", &change.inserted);
+    }
+
+    output
+}
+
+pub fn to_ed_script(patch: &Patch<'_, str>) -> String {
+    let mut output = String::new();
+
+    // Changes are made from the end of the file backwards so that the line numbers of the
+    // earlier changes stay valid
+    for change in changes(patch).iter().rev() {
+        let command = change.command();
+        writeln!(
+            output,
+            "{}{}",
+            normal_range(change.old_position, change.deleted.len()),
+            command
+        )
+        .unwrap();
+
+        if command == 'd' {
+            continue;
+        }
+        for line in &change.inserted {
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            if line == "." {
+                // A lone "." would end the input, so write ".." instead, end the input right
+                // after it, fix it up and continue appending
+                output.push_str("..\n.\ns/.//\na\n");
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+        output.push_str(".\n");
+    }
+
+    output
+}
+
+// A run of deleted and inserted lines, positioned by the number of lines preceding it in the
+// old and new files
+struct Change<'a> {
+    old_position: usize,
+    new_position: usize,
+    deleted: Vec<&'a str>,
+    inserted: Vec<&'a str>,
+}
+
+impl Change<'_> {
+    fn command(&self) -> char {
+        match (self.deleted.is_empty(), self.inserted.is_empty()) {
+            (false, false) => 'c',
+            (false, true) => 'd',
+            _ => 'a',
+        }
+    }
+}
+
+fn changes<'a>(patch: &Patch<'a, str>) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+
+    for hunk in patch.hunks() {
+        let mut old_line = hunk.old_range().position();
+        let mut new_line = hunk.new_range().position();
+        let mut lines = hunk.lines().iter().peekable();
+
+        while let Some(line) = lines.next() {
+            if let Line::Context(_) = line {
+                old_line += 1;
+                new_line += 1;
+                continue;
+            }
+
+            let mut change = Change {
+                old_position: old_line,
+                new_position: new_line,
+                deleted: Vec::new(),
+                inserted: Vec::new(),
+            };
+            let mut line = Some(line);
+            while let Some(l) = line {
+                match l {
+                    Line::Delete(l) => change.deleted.push(*l),
+                    Line::Insert(l) => change.inserted.push(*l),
+                    Line::Context(_) => unreachable!(),
+                }
+                line = lines.next_if(|l| !matches!(l, Line::Context(_)));
+            }
+
+            old_line += change.deleted.len();
+            new_line += change.inserted.len();
+            changes.push(change);
+        }
+    }
+
+    changes
+}
+
+// Renders the range of `len` lines following the first `position` lines of a file. An empty
+// range is rendered as the line preceding it.
+fn normal_range(position: usize, len: usize) -> String {
+    match len {
+        0 => position.to_string(),
+        1 => (position + 1).to_string(),
+        _ => format!("{},{}", position + 1, position + len),
+    }
+}
+
+fn push_lines(output: &mut String, prefix: &str, lines: &[&str]) {
+    for line in lines {
+        output.push_str(prefix);
+        output.push_str(line);
+        if !line.ends_with('\n') {
+            output.push('\n');
+            output.push_str(NO_NEWLINE_AT_EOF);
+            output.push('\n');
+        }
+    }
+}
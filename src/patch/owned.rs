@@ -0,0 +1,107 @@
+use super::{Hunk, HunkRange, IndexLine, Line, LineKind, Patch};
+use std::{fmt, ops};
+
+/// A [`Patch`] which owns its content instead of borrowing it from the texts it was created or
+/// parsed from
+///
+/// Created with [`Patch::into_owned`]. Use [`as_patch`](OwnedPatch::as_patch) to get a `Patch`
+/// borrowing from it again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedPatch {
+    original: Option<Box<str>>,
+    modified: Option<Box<str>>,
+    // The content of every line and function context of the patch, concatenated
+    content: String,
+    hunks: Vec<OwnedHunk>,
+    index: Option<Box<IndexLine>>,
+    binary: Option<Vec<ops::Range<usize>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct OwnedHunk {
+    old_range: HunkRange,
+    new_range: HunkRange,
+    function_context: Option<ops::Range<usize>>,
+    lines: Vec<(LineKind, ops::Range<usize>)>,
+}
+
+impl OwnedPatch {
+    pub(super) fn new(patch: Patch<'_, str>) -> Self {
+        let mut content = String::new();
+        let mut push = |s: &str| {
+            let start = content.len();
+            content.push_str(s);
+            start..content.len()
+        };
+
+        let hunks = patch
+            .hunks()
+            .iter()
+            .map(|hunk| OwnedHunk {
+                old_range: hunk.old_range(),
+                new_range: hunk.new_range(),
+                function_context: hunk.function_context().map(&mut push),
+                lines: hunk
+                    .lines()
+                    .iter()
+                    .map(|line| (line.kind(), push(line.content())))
+                    .collect(),
+            })
+            .collect();
+        let binary = patch
+            .binary()
+            .map(|lines| lines.iter().copied().map(&mut push).collect());
+
+        Self {
+            original: patch.original().map(Into::into),
+            modified: patch.modified().map(Into::into),
+            content,
+            hunks,
+            index: patch.index.clone(),
+            binary,
+        }
+    }
+
+    /// Returns a `Patch` borrowing from this one
+    pub fn as_patch(&self) -> Patch<'_, str> {
+        let hunks = self
+            .hunks
+            .iter()
+            .map(|hunk| {
+                let lines = hunk
+                    .lines
+                    .iter()
+                    .map(|(kind, range)| {
+                        let line = &self.content[range.clone()];
+                        match kind {
+                            LineKind::Context => Line::Context(line),
+                            LineKind::Delete => Line::Delete(line),
+                            LineKind::Insert => Line::Insert(line),
+                        }
+                    })
+                    .collect();
+                let function_context = hunk
+                    .function_context
+                    .clone()
+                    .map(|range| &self.content[range]);
+                Hunk::new(hunk.old_range, hunk.new_range, function_context, lines)
+            })
+            .collect();
+
+        let mut patch = Patch::new(self.original.as_deref(), self.modified.as_deref(), hunks);
+        patch.index = self.index.clone();
+        patch.binary = self.binary.as_ref().map(|lines| {
+            lines
+                .iter()
+                .map(|range| &self.content[range.clone()])
+                .collect()
+        });
+        patch
+    }
+}
+
+impl fmt::Display for OwnedPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_patch().fmt(f)
+    }
+}
@@ -1,12 +1,14 @@
 //! Parse a Patch
 
-use super::{Hunk, HunkRange, Line, ESCAPED_CHARS_BYTES, NO_NEWLINE_AT_EOF};
+use super::{Hunk, HunkRange, IndexLine, Line, ESCAPED_CHARS_BYTES, NO_NEWLINE_AT_EOF};
 use crate::{
     patch::Patch,
     utils::{LineIter, Text},
 };
 use std::{borrow::Cow, fmt};
 
+const BINARY_PATCH: &str = "GIT binary patch";
+
 type Result<T, E = ParsePatchError> = std::result::Result<T, E>;
 
 /// An error returned when parsing a `Patch` using [`Patch::from_str`] fails
@@ -17,7 +19,7 @@ type Result<T, E = ParsePatchError> = std::result::Result<T, E>;
 pub struct ParsePatchError(Cow<'static, str>);
 
 impl ParsePatchError {
-    fn new<E: Into<Cow<'static, str>>>(e: E) -> Self {
+    pub(crate) fn new<E: Into<Cow<'static, str>>>(e: E) -> Self {
         Self(e.into())
     }
 }
@@ -57,21 +59,29 @@ impl<'a, T: Text + ?Sized> Parser<'a, T> {
 pub fn parse(input: &str) -> Result<Patch<'_, str>> {
     let mut parser = Parser::new(input);
     let header = patch_header(&mut parser)?;
+    let binary = binary_patch(&mut parser);
     let hunks = hunks(&mut parser)?;
 
-    Ok(Patch::new(
+    let mut patch = Patch::new(
         header.0.map(convert_cow_to_str),
         header.1.map(convert_cow_to_str),
         hunks,
-    ))
+    );
+    patch.index = header.2.map(Box::new);
+    patch.binary = binary;
+    Ok(patch)
 }
 
 pub fn parse_bytes(input: &[u8]) -> Result<Patch<'_, [u8]>> {
     let mut parser = Parser::new(input);
     let header = patch_header(&mut parser)?;
+    let binary = binary_patch(&mut parser);
     let hunks = hunks(&mut parser)?;
 
-    Ok(Patch::new(header.0, header.1, hunks))
+    let mut patch = Patch::new(header.0, header.1, hunks);
+    patch.index = header.2.map(Box::new);
+    patch.binary = binary;
+    Ok(patch)
 }
 
 // This is only used when the type originated as a utf8 string
@@ -85,8 +95,12 @@ fn convert_cow_to_str(cow: Cow<'_, [u8]>) -> Cow<'_, str> {
 #[allow(clippy::type_complexity)]
 fn patch_header<'a, T: Text + ToOwned + ?Sized>(
     parser: &mut Parser<'a, T>,
-) -> Result<(Option<Cow<'a, [u8]>>, Option<Cow<'a, [u8]>>)> {
-    skip_header_preamble(parser)?;
+) -> Result<(
+    Option<Cow<'a, [u8]>>,
+    Option<Cow<'a, [u8]>>,
+    Option<IndexLine>,
+)> {
+    let index = skip_header_preamble(parser)?;
 
     let mut filename1 = None;
     let mut filename2 = None;
@@ -107,20 +121,60 @@ fn patch_header<'a, T: Text + ToOwned + ?Sized>(
         }
     }
 
-    Ok((filename1, filename2))
+    Ok((filename1, filename2, index))
 }
 
-// Skip to the first filename header ("--- " or "+++ ") or hunk line,
-// skipping any preamble lines like "diff --git", etc.
-fn skip_header_preamble<T: Text + ?Sized>(parser: &mut Parser<'_, T>) -> Result<()> {
+// Skip to the first filename header ("--- " or "+++ "), hunk line or binary patch,
+// skipping any preamble lines like "diff --git", etc. A git "index" line is kept.
+fn skip_header_preamble<T: Text + ?Sized>(parser: &mut Parser<'_, T>) -> Result<Option<IndexLine>> {
+    let mut index = None;
     while let Some(line) = parser.peek() {
-        if line.starts_with("--- ") | line.starts_with("+++ ") | line.starts_with("@@ ") {
+        if line.starts_with("--- ")
+            | line.starts_with("+++ ")
+            | line.starts_with("@@ ")
+            | line.starts_with(BINARY_PATCH)
+        {
             break;
         }
-        parser.next()?;
+        if let Some(line) = index_line(parser.next()?.as_bytes()) {
+            index = Some(line);
+        }
+    }
+
+    Ok(index)
+}
+
+// Parses an "index <old>..<new> [<mode>]" line, where the object ids are hex and the mode is
+// octal. Anything else is left to be skipped with the rest of the preamble.
+fn index_line(line: &[u8]) -> Option<IndexLine> {
+    let line = std::str::from_utf8(line).ok()?;
+    let line = line.strip_suffix('\n')?.strip_prefix("index ")?;
+    let (ids, mode) = match line.split_once(' ') {
+        Some((ids, mode)) => (ids, Some(mode)),
+        None => (line, None),
+    };
+    let (old_id, new_id) = ids.split_once("..")?;
+
+    let is_id = |id: &str| !id.is_empty() && id.bytes().all(|b| b.is_ascii_hexdigit());
+    let is_mode = |mode: &str| !mode.is_empty() && mode.bytes().all(|b| matches!(b, b'0'..=b'7'));
+    if !is_id(old_id) || !is_id(new_id) || !mode.map_or(true, is_mode) {
+        return None;
     }
 
-    Ok(())
+    Some(IndexLine {
+        old_id: old_id.to_owned(),
+        new_id: new_id.to_owned(),
+        mode: mode.map(ToOwned::to_owned),
+    })
+}
+
+// A binary patch has no hunks, so everything from its "GIT binary patch" line to the end of the
+// input belongs to it
+fn binary_patch<'a, T: Text + ?Sized>(parser: &mut Parser<'a, T>) -> Option<Vec<&'a T>> {
+    if !parser.peek()?.starts_with(BINARY_PATCH) {
+        return None;
+    }
+    Some(parser.lines.by_ref().collect())
 }
 
 fn parse_filename<'a, T: Text + ToOwned + ?Sized>(
@@ -191,7 +245,7 @@ fn escaped_filename<T: Text + ToOwned + ?Sized>(escaped: &T) -> Result<Cow<'_, [
     Ok(filename.into())
 }
 
-fn verify_hunks_in_order<T: ?Sized>(hunks: &[Hunk<'_, T>]) -> bool {
+pub fn verify_hunks_in_order<T: ?Sized>(hunks: &[Hunk<'_, T>]) -> bool {
     for hunk in hunks.windows(2) {
         if hunk[0].old_range.end() > hunk[1].old_range.start()
             || hunk[0].new_range.end() > hunk[1].new_range.start()
@@ -229,7 +283,7 @@ fn hunk<'a, T: Text + ?Sized>(parser: &mut Parser<'a, T>) -> Result<Hunk<'a, T>>
     Ok(Hunk::new(range1, range2, function_context, lines))
 }
 
-fn hunk_header<T: Text + ?Sized>(input: &T) -> Result<(HunkRange, HunkRange, Option<&T>)> {
+pub fn hunk_header<T: Text + ?Sized>(input: &T) -> Result<(HunkRange, HunkRange, Option<&T>)> {
     let input = input
         .strip_prefix("@@ ")
         .ok_or_else(|| ParsePatchError::new("unable to parse hunk header"))?;
@@ -237,7 +291,9 @@ fn hunk_header<T: Text + ?Sized>(input: &T) -> Result<(HunkRange, HunkRange, Opt
     let (ranges, function_context) = input
         .split_at_exclusive(" @@")
         .ok_or_else(|| ParsePatchError::new("hunk header unterminated"))?;
-    let function_context = function_context.strip_prefix(" ");
+    let function_context = function_context
+        .strip_prefix(" ")
+        .map(|ctx| ctx.strip_suffix("\n").unwrap_or(ctx));
 
     let (range1, range2) = ranges
         .split_at_exclusive(" ")
@@ -339,7 +395,8 @@ fn strip_newline<T: Text + ?Sized>(s: &T) -> Result<&T> {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse, parse_bytes};
+    use super::{hunk_header, parse, parse_bytes};
+    use crate::patch::HunkRange;
 
     #[test]
     fn test_escaped_filenames() {
@@ -473,4 +530,138 @@ mod tests {
 ";
         parse(s).unwrap();
     }
+
+    #[test]
+    fn hunk_range_parse() {
+        let (old, new) = HunkRange::parse("@@ -1,4 +1,6 @@").unwrap();
+        assert_eq!((old.start(), old.len()), (1, 4));
+        assert_eq!((new.start(), new.len()), (1, 6));
+
+        // Compact single line form
+        let (old, new) = HunkRange::parse("@@ -4 +2,2 @@\n").unwrap();
+        assert_eq!((old.start(), old.len()), (4, 1));
+        assert_eq!((new.start(), new.len()), (2, 2));
+
+        // Section header after the second '@@'
+        let (old, new) = HunkRange::parse("@@ -11,0 +11,3 @@ fn main() {").unwrap();
+        assert_eq!((old.start(), old.len()), (11, 0));
+        assert_eq!((new.start(), new.len()), (11, 3));
+
+        HunkRange::parse("-1,4 +1,6").unwrap_err();
+        HunkRange::parse("@@ -1,4 +1,6").unwrap_err();
+        HunkRange::parse("@@ 1,4 1,6 @@").unwrap_err();
+        HunkRange::parse("@@ -a,4 +1,6 @@").unwrap_err();
+    }
+
+    #[test]
+    fn function_context_roundtrip() {
+        let s = "\
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@ use std::io;
+ use std::fmt;
+-const A: u8 = 1;
++const A: u8 = 2;
+ const B: u8 = 3;
+@@ -10,3 +10,3 @@ fn main() {
+     let a = A;
+-    println!(\"{}\", a);
++    print!(\"{}\", a);
+ }
+";
+        let patch = parse(s).unwrap();
+        let contexts: Vec<_> = patch
+            .hunks()
+            .iter()
+            .map(|hunk| hunk.function_context())
+            .collect();
+        assert_eq!(contexts, [Some("use std::io;"), Some("fn main() {")]);
+        assert_eq!(patch.to_string(), s);
+
+        let bytes = parse_bytes(s.as_bytes()).unwrap();
+        assert_eq!(bytes.to_bytes(), s.as_bytes());
+    }
+
+    #[test]
+    fn binary_patch_roundtrip() {
+        let s = "\
+--- a/logo.png
++++ b/logo.png
+GIT binary patch
+literal 4
+LcmZ?wbhHKl0|5X6
+
+literal 0
+HcmV?d00001
+
+";
+        let patch = parse(s).unwrap();
+        assert!(patch.hunks().is_empty());
+        assert_eq!(patch.binary().unwrap().len(), 7);
+        assert_eq!(patch.to_string(), s);
+        assert_eq!(patch.clone().into_owned().to_string(), s);
+        assert!(patch.reverse().binary().is_none());
+
+        let bytes = parse_bytes(s.as_bytes()).unwrap();
+        assert_eq!(bytes.to_bytes(), s.as_bytes());
+
+        // Without a binary section the preamble is skipped as before
+        let patch = parse("diff --git a/a b/a\nindex 1..2\n").unwrap();
+        assert!(patch.binary().is_none());
+    }
+
+    #[test]
+    fn index_line_roundtrip() {
+        let s = "\
+index 257cc56..3bd1f0e 100644
+--- a/file.txt
++++ b/file.txt
+@@ -1,2 +1,2 @@
+ foo
+-bar
++baz
+";
+        let patch = parse(s).unwrap();
+        let index = patch.index().unwrap();
+        assert_eq!(
+            (index.old_id(), index.new_id(), index.mode()),
+            ("257cc56", "3bd1f0e", Some("100644"))
+        );
+        assert_eq!(patch.to_string(), s);
+        assert_eq!(patch.clone().into_owned().to_string(), s);
+        assert_eq!(parse_bytes(s.as_bytes()).unwrap().to_bytes(), s.as_bytes());
+
+        // The rest of the git preamble is still skipped
+        let git = format!("diff --git a/file.txt b/file.txt\n{}", s);
+        assert_eq!(parse(&git).unwrap().to_string(), s);
+
+        let reversed = patch.reverse().to_string();
+        assert!(reversed.starts_with("index 3bd1f0e..257cc56 100644\n--- b/file.txt\n"));
+
+        // Without a mode, e.g. when the mode changed, or with an unrecognized format
+        let patch = parse("index 257cc56..3bd1f0e\n").unwrap();
+        assert_eq!(patch.index().unwrap().mode(), None);
+        assert_eq!(patch.to_string(), "index 257cc56..3bd1f0e\n");
+        for line in [
+            "index 257cc56\n",
+            "index 257cc56..xyz\n",
+            "index a..b 10064x\n",
+        ] {
+            let patch = parse(line).unwrap();
+            assert!(patch.index().is_none());
+            assert_eq!(patch.to_string(), "");
+        }
+    }
+
+    #[test]
+    fn section_containing_at_signs() {
+        let header = "@@ -1,3 +1,4 @@ fn f() -> &'static str { \"@@ x @@\" }\n";
+        let (old, new, function_context) = hunk_header(header).unwrap();
+        assert_eq!((old.start(), old.len()), (1, 3));
+        assert_eq!((new.start(), new.len()), (1, 4));
+        assert_eq!(
+            function_context,
+            Some("fn f() -> &'static str { \"@@ x @@\" }")
+        );
+    }
 }
@@ -0,0 +1,81 @@
+use super::Patch;
+use std::fmt;
+
+/// The patches of several files, e.g. of a changeset, created with [`DiffBuilder`]
+///
+/// Its display is a multi-file unified diff, the patch of each file following the previous one.
+///
+/// [`DiffBuilder`]: crate::DiffBuilder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchSet<'a> {
+    patches: Vec<Patch<'a, str>>,
+}
+
+impl<'a> PatchSet<'a> {
+    pub(crate) fn new(patches: Vec<Patch<'a, str>>) -> Self {
+        Self { patches }
+    }
+
+    /// Returns the patches of the files, in the order they were added
+    pub fn patches(&self) -> &[Patch<'a, str>] {
+        &self.patches
+    }
+
+    /// Returns the number of files in the set
+    pub fn len(&self) -> usize {
+        self.patches.len()
+    }
+
+    /// Returns `true` if the set doesn't contain any files
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    /// Render a summary of the set in the style of `git diff --stat`
+    ///
+    /// There is a line for each file like the one of [`Patch::stat_summary`], with the graphs of
+    /// all files scaled alike so that the longest line fits in `width` columns, followed by a
+    /// line with the totals of all files. Files are named without the `a/` and `b/` prefixes of
+    /// their patches. An empty set has an empty summary.
+    ///
+    /// ```
+    /// use diffy::DiffBuilder;
+    ///
+    /// let patches = DiffBuilder::new()
+    ///     .add_file("src/lib.rs", "a\nb\n", "a\nc\n", 3)
+    ///     .add_file("README.md", "", "diffy\n", 3)
+    ///     .build();
+    /// assert_eq!(
+    ///     patches.stat_summary(80),
+    ///     " src/lib.rs | 2 +-\n \
+    ///       README.md  | 1 +\n \
+    ///       2 files changed, 2 insertions(+), 1 deletion(-)\n"
+    /// );
+    /// ```
+    pub fn stat_summary(&self, width: usize) -> String {
+        if self.patches.is_empty() {
+            return String::new();
+        }
+        let files: Vec<_> = self
+            .patches
+            .iter()
+            .map(|patch| (patch.display_name().unwrap_or_default(), patch.stats()))
+            .collect();
+        super::stat_summary(&files, width)
+    }
+}
+
+impl<'a> IntoIterator for PatchSet<'a> {
+    type Item = Patch<'a, str>;
+    type IntoIter = std::vec::IntoIter<Patch<'a, str>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.patches.into_iter()
+    }
+}
+
+impl fmt::Display for PatchSet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.patches.iter().try_for_each(|patch| patch.fmt(f))
+    }
+}
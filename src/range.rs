@@ -82,9 +82,17 @@ impl<'a, T: ?Sized> Range<'a, T> {
         })
     }
 
+    /// Divides the range in two at `mid`, the first half covering `..mid`
+    ///
+    /// Panics if `mid > self.len()`, see `split_at_checked` for a non-panicking version.
     pub fn split_at(&self, mid: usize) -> (Self, Self) {
         (self.slice(..mid), self.slice(mid..))
     }
+
+    /// Divides the range in two at `mid`, returning `None` if `mid > self.len()`
+    pub fn split_at_checked(&self, mid: usize) -> Option<(Self, Self)> {
+        Some((self.get(..mid)?, self.get(mid..)?))
+    }
 }
 
 impl<'a, T> Range<'a, T>
@@ -109,10 +117,12 @@ where
         self.inner.as_slice(self.offset..self.offset + self.len)
     }
 
+    /// Returns the number of leading elements, compared with `PartialEq`, shared with `other`
     pub fn common_prefix_len(&self, other: Range<'_, T>) -> usize {
         self.as_slice().common_prefix_len(other.as_slice())
     }
 
+    /// Returns the number of trailing elements, compared with `PartialEq`, shared with `other`
     pub fn common_suffix_len(&self, other: Range<'_, T>) -> usize {
         self.as_slice().common_suffix_len(other.as_slice())
     }
@@ -344,13 +354,24 @@ where
     }
 }
 
-#[derive(Debug)]
 pub enum DiffRange<'a, 'b, T: ?Sized> {
     Equal(Range<'a, T>, Range<'b, T>),
     Delete(Range<'a, T>),
     Insert(Range<'b, T>),
 }
 
+// Shows the content each range resolves to rather than its offset and length. `Equal` only shows
+// the old side since both sides have the same content.
+impl<T: ?Sized + Debug + SliceLike> Debug for DiffRange<'_, '_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffRange::Equal(range, _) => f.debug_tuple("Equal").field(&range.as_slice()).finish(),
+            DiffRange::Delete(range) => f.debug_tuple("Delete").field(&range.as_slice()).finish(),
+            DiffRange::Insert(range) => f.debug_tuple("Insert").field(&range.as_slice()).finish(),
+        }
+    }
+}
+
 impl<T: ?Sized> Copy for DiffRange<'_, '_, T> {}
 
 impl<T: ?Sized> Clone for DiffRange<'_, '_, T> {
@@ -359,6 +380,24 @@ impl<T: ?Sized> Clone for DiffRange<'_, '_, T> {
     }
 }
 
+impl<T: ?Sized> DiffRange<'_, '_, T> {
+    /// Returns the absolute range of indices in the old text, or `None` for an `Insert`
+    pub fn old_span(&self) -> Option<ops::Range<usize>> {
+        match self {
+            DiffRange::Equal(range, _) | DiffRange::Delete(range) => Some(range.range()),
+            DiffRange::Insert(_) => None,
+        }
+    }
+
+    /// Returns the absolute range of indices in the new text, or `None` for a `Delete`
+    pub fn new_span(&self) -> Option<ops::Range<usize>> {
+        match self {
+            DiffRange::Equal(_, range) | DiffRange::Insert(range) => Some(range.range()),
+            DiffRange::Delete(_) => None,
+        }
+    }
+}
+
 impl<'tmp, 'a: 'tmp, 'b: 'tmp, T> DiffRange<'a, 'b, T>
 where
     T: ?Sized + SliceLike,
@@ -415,8 +454,56 @@ where
     }
 }
 
+/// Returns the pieces of the old text covered by `solution`, in order
+///
+/// Concatenated, they make up the whole old text the solution was computed from.
+#[cfg(test)]
+pub fn reconstruct_old<'a, T>(solution: &[DiffRange<'a, '_, T>]) -> Vec<&'a T>
+where
+    T: ?Sized + SliceLike,
+{
+    solution
+        .iter()
+        .filter_map(|diff| match diff {
+            DiffRange::Equal(range, _) | DiffRange::Delete(range) => Some(range.as_slice()),
+            DiffRange::Insert(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the pieces of the new text covered by `solution`, in order
+///
+/// Concatenated, they make up the whole new text the solution was computed from.
+#[cfg(test)]
+pub fn reconstruct_new<'b, T>(solution: &[DiffRange<'_, 'b, T>]) -> Vec<&'b T>
+where
+    T: ?Sized + SliceLike,
+{
+    solution
+        .iter()
+        .filter_map(|diff| match diff {
+            DiffRange::Equal(_, range) | DiffRange::Insert(range) => Some(range.as_slice()),
+            DiffRange::Delete(_) => None,
+        })
+        .collect()
+}
+
 impl<'a, 'b> DiffRange<'a, 'b, [u8]> {
+    // Converts byte ranges of the utf8 texts they were diffed from into `str` ranges, widening
+    // changed ranges and narrowing equal ranges to the nearest char boundaries.
+    //
+    // Panics if a range lies outside of its text. Ranges never end up splitting a char.
     pub fn to_str(self, text1: &'a str, text2: &'b str) -> DiffRange<'a, 'b, str> {
+        fn check_bounds(text: &str, range: Range<'_, [u8]>) {
+            assert!(
+                range.offset() + range.len() <= text.len(),
+                "diff range {}..{} is out of bounds of a text of {} bytes",
+                range.offset(),
+                range.offset() + range.len(),
+                text.len()
+            );
+        }
+
         fn boundary_down(text: &str, pos: usize) -> usize {
             let mut adjust = 0;
             while !text.is_char_boundary(pos - adjust) {
@@ -437,6 +524,8 @@ impl<'a, 'b> DiffRange<'a, 'b, [u8]> {
             DiffRange::Equal(range1, range2) => {
                 debug_assert_eq!(range1.inner().as_ptr(), text1.as_ptr());
                 debug_assert_eq!(range2.inner().as_ptr(), text2.as_ptr());
+                check_bounds(text1, range1);
+                check_bounds(text2, range2);
                 let mut offset1 = range1.offset();
                 let mut len1 = range1.len();
                 let mut offset2 = range2.offset();
@@ -458,6 +547,7 @@ impl<'a, 'b> DiffRange<'a, 'b, [u8]> {
             }
             DiffRange::Delete(range) => {
                 debug_assert_eq!(range.inner().as_ptr(), text1.as_ptr());
+                check_bounds(text1, range);
                 let mut offset = range.offset();
                 let mut len = range.len();
                 let adjust = boundary_down(text1, offset);
@@ -469,6 +559,7 @@ impl<'a, 'b> DiffRange<'a, 'b, [u8]> {
             }
             DiffRange::Insert(range) => {
                 debug_assert_eq!(range.inner().as_ptr(), text2.as_ptr());
+                check_bounds(text2, range);
                 let mut offset = range.offset();
                 let mut len = range.len();
                 let adjust = boundary_down(text2, offset);
@@ -518,6 +609,26 @@ mod tests {
         let text1 = Range::new(snowman.as_bytes(), ..);
         let text2 = Range::new(comet.as_bytes(), ..);
         assert_eq!(2, text1.common_prefix_len(text2), "Unicode case");
+
+        let text1: Range<str> = Range::empty();
+        let text2 = Range::new("abc", ..);
+        assert_eq!(0, text1.common_prefix_len(text2), "Empty case");
+        assert_eq!(0, text2.common_prefix_len(text1), "Empty case");
+
+        let text1 = Range::new("abc", ..);
+        let text2 = Range::new("abc", ..);
+        assert_eq!(3, text1.common_prefix_len(text2), "Identical case");
+
+        let lines1 = ["a", "b", "c"];
+        let lines2 = ["a", "b", "x"];
+        let text1 = Range::new(&lines1[..], ..);
+        let text2 = Range::new(&lines2[..], ..);
+        assert_eq!(2, text1.common_prefix_len(text2), "Slice case");
+        assert_eq!(
+            1,
+            text1.slice(1..).common_prefix_len(text2.slice(1..)),
+            "Subrange case"
+        );
     }
 
     #[test]
@@ -542,6 +653,44 @@ mod tests {
         let text1 = Range::new(b"1234".as_ref(), ..);
         let text2 = Range::new(b"xyz1234".as_ref(), ..);
         assert_eq!(4, text1.common_suffix_len(text2), "Whole case");
+
+        let text1: Range<str> = Range::empty();
+        let text2 = Range::new("abc", ..);
+        assert_eq!(0, text1.common_suffix_len(text2), "Empty case");
+        assert_eq!(0, text2.common_suffix_len(text1), "Empty case");
+
+        let text1 = Range::new("abc", ..);
+        let text2 = Range::new("abc", ..);
+        assert_eq!(3, text1.common_suffix_len(text2), "Identical case");
+
+        let lines1 = ["a", "b", "c"];
+        let lines2 = ["x", "b", "c"];
+        let text1 = Range::new(&lines1[..], ..);
+        let text2 = Range::new(&lines2[..], ..);
+        assert_eq!(2, text1.common_suffix_len(text2), "Slice case");
+        assert_eq!(
+            1,
+            text1.slice(..2).common_suffix_len(text2.slice(..2)),
+            "Subrange case"
+        );
+    }
+
+    #[test]
+    fn test_diff_range_span() {
+        let old = "abcdef";
+        let new = "xcdefy";
+
+        let equal = DiffRange::Equal(Range::new(old, 2..6), Range::new(new, 1..5));
+        assert_eq!(equal.old_span(), Some(2..6));
+        assert_eq!(equal.new_span(), Some(1..5));
+
+        let delete = DiffRange::Delete(Range::new(old, 0..2));
+        assert_eq!(delete.old_span(), Some(0..2));
+        assert_eq!(delete.new_span(), None);
+
+        let insert: DiffRange<str> = DiffRange::Insert(Range::new(new, 5..6));
+        assert_eq!(insert.old_span(), None);
+        assert_eq!(insert.new_span(), Some(5..6));
     }
 
     #[test]
@@ -580,4 +729,69 @@ mod tests {
         let text2 = Range::new("\u{fb01}i", ..);
         assert_eq!(0, text1.common_overlap_len(text2), "Unicode");
     }
+
+    #[test]
+    fn diff_range_debug_shows_content() {
+        let old = Range::new("xabcx", 1..4);
+        let new = Range::new("abc", ..);
+        assert_eq!(
+            format!("{:?}", DiffRange::Equal(old, new)),
+            r#"Equal("abc")"#
+        );
+        assert_eq!(format!("{:?}", DiffRange::Delete(old)), r#"Delete("abc")"#);
+        assert_eq!(
+            format!("{:?}", DiffRange::<str>::Insert(Range::empty())),
+            r#"Insert("")"#
+        );
+
+        let bytes = Range::new(b"ab".as_ref(), ..);
+        assert_eq!(
+            format!("{:?}", DiffRange::Insert(bytes)),
+            "Insert([97, 98])"
+        );
+    }
+
+    #[test]
+    fn to_str_snaps_to_char_boundaries() {
+        let (text1, text2) = ("aé", "aè");
+        let (bytes1, bytes2) = (text1.as_bytes(), text2.as_bytes());
+
+        // The equal prefix includes the first byte of `é` and `è`, which is the same
+        let equal = DiffRange::Equal(Range::new(bytes1, 0..2), Range::new(bytes2, 0..2));
+        let delete = DiffRange::Delete(Range::new(bytes1, 2..3));
+        let insert = DiffRange::Insert(Range::new(bytes2, 2..3));
+
+        let str_ranges = [equal, delete, insert].map(|range| range.to_str(text1, text2));
+        assert_eq!(
+            format!("{:?}", str_ranges),
+            r#"[Equal("a"), Delete("é"), Insert("è")]"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "diff range 2..5 is out of bounds of a text of 3 bytes")]
+    fn to_str_out_of_bounds() {
+        let text = "abcdef";
+        DiffRange::Delete(Range::new(text.as_bytes(), 2..5)).to_str(&text[..3], text);
+    }
+
+    #[test]
+    fn split_at_checked() {
+        let range = Range::new("xabcx", 1..4);
+
+        let (a, b) = range.split_at_checked(1).unwrap();
+        assert_eq!((a.as_slice(), b.as_slice()), ("a", "bc"));
+        let (a, b) = range.split_at_checked(3).unwrap();
+        assert_eq!((a.as_slice(), b.as_slice()), ("abc", ""));
+
+        // Past the end of the range, even though it's within the underlying text
+        assert!(range.split_at_checked(4).is_none());
+        assert!(range.split_at_checked(usize::MAX).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of range")]
+    fn split_at_out_of_range() {
+        Range::new("xabcx", 1..4).split_at(4);
+    }
 }
@@ -1,19 +1,21 @@
 //! Common utilities
 
 use std::{
+    borrow::Cow,
     collections::{hash_map::Entry, HashMap},
     hash::Hash,
 };
 
 /// Classifies lines, converting lines into unique `u64`s for quicker comparison
-pub struct Classifier<'a, T: ?Sized> {
+pub struct Classifier<'a, T: ?Sized + ToOwned> {
     next_id: u64,
-    unique_ids: HashMap<&'a T, u64>,
+    unique_ids: HashMap<Cow<'a, T>, u64>,
 }
 
-impl<'a, T: ?Sized + Eq + Hash> Classifier<'a, T> {
-    fn classify(&mut self, record: &'a T) -> u64 {
-        match self.unique_ids.entry(record) {
+impl<'a, T: ?Sized + ToOwned + Eq + Hash> Classifier<'a, T> {
+    /// Classifies a key which may have been derived from, rather than borrowed from, a record
+    pub fn classify_key(&mut self, key: Cow<'a, T>) -> u64 {
+        match self.unique_ids.entry(key) {
             Entry::Occupied(o) => *o.get(),
             Entry::Vacant(v) => {
                 let id = self.next_id;
@@ -24,15 +26,7 @@ impl<'a, T: ?Sized + Eq + Hash> Classifier<'a, T> {
     }
 }
 
-impl<'a, T: ?Sized + Text> Classifier<'a, T> {
-    pub fn classify_lines(&mut self, text: &'a T) -> (Vec<&'a T>, Vec<u64>) {
-        LineIter::new(text)
-            .map(|line| (line, self.classify(line)))
-            .unzip()
-    }
-}
-
-impl<T: Eq + Hash + ?Sized> Default for Classifier<'_, T> {
+impl<T: Eq + Hash + ?Sized + ToOwned> Default for Classifier<'_, T> {
     fn default() -> Self {
         Self {
             next_id: 0,
@@ -41,6 +35,105 @@ impl<T: Eq + Hash + ?Sized> Default for Classifier<'_, T> {
     }
 }
 
+/// How line endings are treated when diffing, applying and merging
+///
+/// With `Lf` or `CrLf`, lines are compared regardless of whether they end in `\r\n` or `\n`
+/// and every line an operation writes out is given that line ending.
+///
+/// `Auto` detects the line ending from the base file: the image a patch is applied to, the
+/// common ancestor of a merge or the original file of a diff. If most of its lines end in
+/// `\r\n` it behaves like `CrLf` except that lines taken from the base file are written as they
+/// are, and so are lines whose line ending was changed on purpose: those of a hunk which matches
+/// exactly or changes line endings itself, and those of a side of a merge which mostly uses the
+/// other line ending. Otherwise lines are compared and written exactly as they are.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Unix line endings, `\n`
+    Lf,
+    /// Windows line endings, `\r\n`
+    CrLf,
+    /// Detect the line ending from the base file
+    #[default]
+    Auto,
+}
+
+impl LineEnding {
+    /// Resolves `Auto` against the lines of the base file, returning `None` if lines should be
+    /// compared and written exactly
+    pub(crate) fn resolve<'a, T: Text + ?Sized + 'a>(
+        self,
+        base: impl IntoIterator<Item = &'a T>,
+    ) -> Option<LineEnding> {
+        match self {
+            LineEnding::Auto => {
+                let (crlf, lf) = base.into_iter().fold((0, 0), |(crlf, lf), line| {
+                    if line.ends_with("\r\n") {
+                        (crlf + 1, lf)
+                    } else if line.ends_with("\n") {
+                        (crlf, lf + 1)
+                    } else {
+                        (crlf, lf)
+                    }
+                });
+                (crlf > lf).then_some(LineEnding::CrLf)
+            }
+            ending => Some(ending),
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Lf | LineEnding::Auto => "\n",
+        }
+    }
+
+    /// Returns the line with its line ending replaced by this one, as its content without a
+    /// line ending followed by the line ending to write, if it needs replacing
+    pub(crate) fn convert<T: Text + ?Sized>(self, line: &T) -> (&T, Option<&'static str>) {
+        let (content, ending) = match line.strip_suffix("\r\n") {
+            Some(content) => (content, "\r\n"),
+            None => match line.strip_suffix("\n") {
+                Some(content) => (content, "\n"),
+                None => return (line, None),
+            },
+        };
+        if ending == self.as_str() {
+            (line, None)
+        } else {
+            (content, Some(self.as_str()))
+        }
+    }
+}
+
+/// Returns the key to compare a line by when ignoring the difference between `\r\n` and `\n`
+pub(crate) fn without_cr(line: &[u8]) -> Cow<'_, [u8]> {
+    match line.strip_suffix(b"\r\n") {
+        Some(content) => {
+            let mut key = content.to_vec();
+            key.push(b'\n');
+            Cow::Owned(key)
+        }
+        None => Cow::Borrowed(line),
+    }
+}
+
+// Splits a line into alternating runs of whitespace and non-whitespace
+pub(crate) fn split_words(line: &[u8]) -> Vec<&[u8]> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    for i in 1..line.len() {
+        if line[i].is_ascii_whitespace() != line[i - 1].is_ascii_whitespace() {
+            words.push(&line[start..i]);
+            start = i;
+        }
+    }
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
+
 /// Iterator over the lines of a string, including the `\n` character.
 pub struct LineIter<'a, T: ?Sized>(&'a T);
 
@@ -72,11 +165,10 @@ impl<'a, T: Text + ?Sized> Iterator for LineIter<'a, T> {
 
 /// A helper trait for processing text like `str` and `[u8]`
 /// Useful for abstracting over those types for parsing as well as breaking input into lines
-pub trait Text: Eq + Hash {
+pub trait Text: Eq + Hash + ToOwned {
     fn is_empty(&self) -> bool;
     fn len(&self) -> usize;
     fn starts_with(&self, prefix: &str) -> bool;
-    #[allow(unused)]
     fn ends_with(&self, suffix: &str) -> bool;
     fn strip_prefix(&self, prefix: &str) -> Option<&Self>;
     fn strip_suffix(&self, suffix: &str) -> Option<&Self>;
@@ -86,7 +178,7 @@ pub trait Text: Eq + Hash {
     fn as_str(&self) -> Option<&str>;
     fn as_bytes(&self) -> &[u8];
     #[allow(unused)]
-    fn lines(&self) -> LineIter<Self>;
+    fn lines(&self) -> LineIter<'_, Self>;
 
     fn parse<T: std::str::FromStr>(&self) -> Option<T> {
         self.as_str().and_then(|s| s.parse().ok())
@@ -139,7 +231,7 @@ impl Text for str {
         self.as_bytes()
     }
 
-    fn lines(&self) -> LineIter<Self> {
+    fn lines(&self) -> LineIter<'_, Self> {
         LineIter::new(self)
     }
 }
@@ -189,7 +281,7 @@ impl Text for [u8] {
         self
     }
 
-    fn lines(&self) -> LineIter<Self> {
+    fn lines(&self) -> LineIter<'_, Self> {
         LineIter::new(self)
     }
 }